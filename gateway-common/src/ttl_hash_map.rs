@@ -1,11 +1,15 @@
 //! A hashmap with entries that expire after a given TTL.
 //!
+//! The hashmap can optionally be bounded to a maximum number of entries. When inserting a new key
+//! into a full hashmap, the least-recently used (i.e., inserted, updated or read with
+//! [`get`](TtlHashMap::get)) entry is evicted.
+//!
 //! <div class="warning">
 //! The hashmap expired entries are not automatically removed. You must call
 //! [`cleanup`](TtlHashMap::cleanup) to remove the expired entries and release the unused memory.
 //! </div>
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     time::{Duration, Instant},
 };
 
@@ -16,14 +20,31 @@ pub const DEFAULT_TTL: Duration = Duration::MAX;
 #[derive(Clone)]
 pub struct TtlHashMap<K, V> {
     ttl: Duration,
-    inner: HashMap<K, (Instant, V)>,
+    bound: Option<Bound<K>>,
+    /// The entries, with their write time and recency sequence number.
+    inner: HashMap<K, (Instant, u64, V)>,
+    /// The keys by recency sequence number, least-recently used first. Only tracked if the hashmap
+    /// is bounded.
+    recency: BTreeMap<u64, K>,
+    next_seq: u64,
+}
+
+/// The maximum number of entries of a bounded hashmap.
+#[derive(Clone)]
+struct Bound<K> {
+    max_entries: usize,
+    /// Clones a key into the recency order. Only bounded hashmaps require cloneable keys.
+    clone_key: fn(&K) -> K,
 }
 
 impl<K, V> Default for TtlHashMap<K, V> {
     fn default() -> Self {
         Self {
             ttl: DEFAULT_TTL,
+            bound: None,
             inner: Default::default(),
+            recency: Default::default(),
+            next_seq: 0,
         }
     }
 }
@@ -38,7 +59,7 @@ impl<K, V> TtlHashMap<K, V> {
     pub fn with_ttl(ttl: Duration) -> Self {
         Self {
             ttl,
-            inner: Default::default(),
+            ..Default::default()
         }
     }
 
@@ -47,6 +68,28 @@ impl<K, V> TtlHashMap<K, V> {
         Self {
             ttl,
             inner: HashMap::with_capacity(capacity),
+            ..Default::default()
+        }
+    }
+}
+
+impl<K, V> TtlHashMap<K, V>
+where
+    K: Clone,
+{
+    /// Create a new hashmap with the given TTL, holding at most `max_entries` entries.
+    ///
+    /// Inserting a new key when the hashmap is full evicts the least-recently used entry,
+    /// regardless of whether it has expired.
+    pub fn with_ttl_and_max_entries(ttl: Duration, max_entries: usize) -> Self {
+        Self {
+            ttl,
+            bound: Some(Bound {
+                max_entries,
+                clone_key: K::clone,
+            }),
+            inner: HashMap::with_capacity(max_entries),
+            ..Default::default()
         }
     }
 }
@@ -59,11 +102,26 @@ where
     ///
     /// If the key already exists, the value is updated and the old value is returned.
     /// Otherwise, `None` is returned.
+    ///
+    /// If the hashmap is bounded and full, the least-recently used entry is evicted to make room
+    /// for the new key.
     pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        if let Some(bound) = &self.bound {
+            if !self.inner.contains_key(&key) && self.inner.len() >= bound.max_entries {
+                if let Some((_, key)) = self.recency.pop_first() {
+                    self.inner.remove(&key);
+                }
+            }
+            self.recency.insert(self.next_seq, (bound.clone_key)(&key));
+        }
+
         let now = Instant::now();
+        let seq = self.next_seq;
+        self.next_seq += 1;
         self.inner
-            .insert(key, (now, value))
-            .and_then(|(timestamp, value)| {
+            .insert(key, (now, seq, value))
+            .and_then(|(timestamp, seq, value)| {
+                self.recency.remove(&seq);
                 if timestamp.elapsed() < self.ttl {
                     Some(value)
                 } else {
@@ -72,13 +130,32 @@ where
             })
     }
 
-    /// Get the value associated with the key.
+    /// Get the value associated with the key, marking the entry as the most-recently used.
+    ///
+    /// If the key is found and the entry has not expired, the value is returned. Otherwise,
+    /// `None` is returned.
+    #[must_use]
+    pub fn get(&mut self, key: &K) -> Option<&V> {
+        let (timestamp, seq, value) = self.inner.get_mut(key)?;
+        if timestamp.elapsed() >= self.ttl {
+            return None;
+        }
+
+        if let Some(key) = self.recency.remove(&*seq) {
+            *seq = self.next_seq;
+            self.recency.insert(self.next_seq, key);
+            self.next_seq += 1;
+        }
+        Some(&*value)
+    }
+
+    /// Get the value associated with the key, without affecting its expiry or recency.
     ///
     /// If the key is found and the entry has not expired, the value is returned. Otherwise,
     /// `None` is returned.
     #[must_use]
-    pub fn get(&self, key: &K) -> Option<&V> {
-        self.inner.get(key).and_then(|(timestamp, value)| {
+    pub fn peek(&self, key: &K) -> Option<&V> {
+        self.inner.get(key).and_then(|(timestamp, _, value)| {
             if timestamp.elapsed() < self.ttl {
                 Some(value)
             } else {
@@ -87,12 +164,30 @@ where
         })
     }
 
+    /// Returns whether the hashmap contains a non-expired entry for the key.
+    #[must_use]
+    pub fn contains_key(&self, key: &K) -> bool {
+        self.peek(key).is_some()
+    }
+
+    /// Returns the time remaining until the entry for the key expires.
+    ///
+    /// If the key is not found or the entry has expired, `None` is returned.
+    #[must_use]
+    pub fn ttl_remaining(&self, key: &K) -> Option<Duration> {
+        let (timestamp, _, _) = self.inner.get(key)?;
+        self.ttl
+            .checked_sub(timestamp.elapsed())
+            .filter(|remaining| !remaining.is_zero())
+    }
+
     /// Remove the key and its associated value from the hashmap.
     ///
     /// If the key is found and the entry has not expired, the value is returned. Otherwise,
     /// `None` is returned.
     pub fn remove(&mut self, key: &K) -> Option<V> {
-        self.inner.remove(key).and_then(|(timestamp, value)| {
+        self.inner.remove(key).and_then(|(timestamp, seq, value)| {
+            self.recency.remove(&seq);
             if timestamp.elapsed() < self.ttl {
                 Some(value)
             } else {
@@ -108,7 +203,7 @@ where
     pub fn len(&self) -> usize {
         self.inner
             .iter()
-            .filter(|(_, (timestamp, _))| timestamp.elapsed() < self.ttl)
+            .filter(|(_, (timestamp, _, _))| timestamp.elapsed() < self.ttl)
             .count()
     }
 
@@ -142,6 +237,7 @@ where
     /// Clear the hashmap, removing all entries.
     pub fn clear(&mut self) {
         self.inner.clear();
+        self.recency.clear();
     }
 
     /// Cleanup the hashmap, removing all expired entries.
@@ -151,7 +247,9 @@ where
     pub fn cleanup(&mut self) {
         // Remove all expired entries
         self.inner
-            .retain(|_, (timestamp, _)| timestamp.elapsed() < self.ttl);
+            .retain(|_, (timestamp, _, _)| timestamp.elapsed() < self.ttl);
+        let inner = &self.inner;
+        self.recency.retain(|_, key| inner.contains_key(key));
 
         // Shrink the inner hashmap to fit the new size
         self.shrink_to_fit();
@@ -181,7 +279,7 @@ mod tests {
     #[test]
     fn it_should_get_none_if_no_item_is_present() {
         //* Given
-        let ttl_hash_map = TtlHashMap::<&str, ()>::new();
+        let mut ttl_hash_map = TtlHashMap::<&str, ()>::new();
 
         let key = "item";
 
@@ -393,4 +491,170 @@ mod tests {
         assert!(ttl_hash_map.capacity() < 100);
         assert!(!ttl_hash_map.is_empty());
     }
+
+    #[test]
+    fn it_should_evict_the_least_recently_used_item_when_full() {
+        //* Given
+        let mut ttl_hash_map = TtlHashMap::with_ttl_and_max_entries(DEFAULT_TTL, 2);
+
+        // Pre-populate the map
+        ttl_hash_map.insert("item_1", 1);
+        ttl_hash_map.insert("item_2", 2);
+
+        // Update the first item, so the second item becomes the least-recently used
+        ttl_hash_map.insert("item_1", 11);
+
+        //* When
+        ttl_hash_map.insert("item_3", 3);
+
+        //* Then
+        assert_eq!(ttl_hash_map.get(&"item_1"), Some(&11));
+        assert_eq!(ttl_hash_map.get(&"item_2"), None);
+        assert_eq!(ttl_hash_map.get(&"item_3"), Some(&3));
+        assert_eq!(ttl_hash_map.len(), 2);
+        assert_eq!(ttl_hash_map.len_all(), 2);
+    }
+
+    #[test]
+    fn it_should_refresh_the_eviction_order_on_get() {
+        //* Given
+        let mut ttl_hash_map = TtlHashMap::with_ttl_and_max_entries(DEFAULT_TTL, 2);
+
+        // Pre-populate the map
+        ttl_hash_map.insert("item_1", 1);
+        ttl_hash_map.insert("item_2", 2);
+
+        // Reading the first item makes the second item the least-recently used
+        assert_eq!(ttl_hash_map.get(&"item_1"), Some(&1));
+
+        //* When
+        ttl_hash_map.insert("item_3", 3);
+
+        //* Then
+        assert_eq!(ttl_hash_map.peek(&"item_1"), Some(&1));
+        assert_eq!(ttl_hash_map.peek(&"item_2"), None);
+        assert_eq!(ttl_hash_map.peek(&"item_3"), Some(&3));
+    }
+
+    #[test]
+    fn it_should_not_refresh_the_eviction_order_on_peek() {
+        //* Given
+        let mut ttl_hash_map = TtlHashMap::with_ttl_and_max_entries(DEFAULT_TTL, 2);
+
+        // Pre-populate the map
+        ttl_hash_map.insert("item_1", 1);
+        ttl_hash_map.insert("item_2", 2);
+
+        // Peeking the first item does not protect it from eviction
+        assert_eq!(ttl_hash_map.peek(&"item_1"), Some(&1));
+
+        //* When
+        ttl_hash_map.insert("item_3", 3);
+
+        //* Then
+        assert_eq!(ttl_hash_map.peek(&"item_1"), None);
+        assert_eq!(ttl_hash_map.peek(&"item_2"), Some(&2));
+        assert_eq!(ttl_hash_map.peek(&"item_3"), Some(&3));
+    }
+
+    #[test]
+    fn it_should_evict_in_recency_order_after_removals() {
+        //* Given
+        let mut ttl_hash_map = TtlHashMap::with_ttl_and_max_entries(DEFAULT_TTL, 2);
+
+        ttl_hash_map.insert("item_1", 1);
+        ttl_hash_map.insert("item_2", 2);
+        ttl_hash_map.remove(&"item_1");
+        ttl_hash_map.insert("item_3", 3);
+
+        //* When
+        ttl_hash_map.insert("item_4", 4);
+
+        //* Then
+        assert_eq!(ttl_hash_map.get(&"item_2"), None);
+        assert_eq!(ttl_hash_map.get(&"item_3"), Some(&3));
+        assert_eq!(ttl_hash_map.get(&"item_4"), Some(&4));
+        assert_eq!(ttl_hash_map.len_all(), 2);
+    }
+
+    #[test]
+    fn it_should_expire_items_when_bounded() {
+        //* Given
+        let mut ttl_hash_map = TtlHashMap::with_ttl_and_max_entries(Duration::from_millis(5), 2);
+
+        let key = "item";
+        let value = 1337;
+
+        // Pre-populate the map
+        ttl_hash_map.insert(key, value);
+
+        //* When
+        // Wait for the TTL to expire
+        std::thread::sleep(Duration::from_millis(10));
+
+        //* Then
+        assert_eq!(ttl_hash_map.get(&key), None);
+        assert_eq!(ttl_hash_map.len(), 0);
+        assert_eq!(ttl_hash_map.len_all(), 1);
+    }
+
+    #[test]
+    fn it_should_peek_an_item_and_report_it_is_present() {
+        //* Given
+        let mut ttl_hash_map = TtlHashMap::new();
+
+        let key = "item";
+        let value = 1337;
+
+        // Pre-populate the map
+        ttl_hash_map.insert(key, value);
+
+        //* Then
+        assert_eq!(ttl_hash_map.peek(&key), Some(&value));
+        assert!(ttl_hash_map.contains_key(&key));
+        assert!(!ttl_hash_map.contains_key(&"missing_item"));
+    }
+
+    #[test]
+    fn it_should_report_a_decreasing_ttl_remaining() {
+        //* Given
+        let mut ttl_hash_map = TtlHashMap::with_ttl(Duration::from_millis(50));
+
+        let key = "item";
+        let value = 1337;
+
+        // Pre-populate the map
+        ttl_hash_map.insert(key, value);
+        let initial_ttl = ttl_hash_map.ttl_remaining(&key).expect("item not expired");
+
+        //* When
+        std::thread::sleep(Duration::from_millis(10));
+
+        //* Then
+        let remaining_ttl = ttl_hash_map.ttl_remaining(&key).expect("item not expired");
+        assert!(remaining_ttl < initial_ttl);
+        assert!(remaining_ttl <= Duration::from_millis(40));
+    }
+
+    #[test]
+    fn it_should_report_no_ttl_remaining_if_expired_or_absent() {
+        //* Given
+        let mut ttl_hash_map = TtlHashMap::with_ttl(Duration::from_millis(5));
+
+        let key = "item";
+        let value = 1337;
+
+        // Pre-populate the map
+        ttl_hash_map.insert(key, value);
+
+        //* When
+        // Wait for the TTL to expire
+        std::thread::sleep(Duration::from_millis(10));
+
+        //* Then
+        assert_eq!(ttl_hash_map.ttl_remaining(&key), None);
+        assert_eq!(ttl_hash_map.ttl_remaining(&"missing_item"), None);
+        assert_eq!(ttl_hash_map.peek(&key), None);
+        assert!(!ttl_hash_map.contains_key(&key));
+    }
 }
@@ -1,4 +1,4 @@
-use std::time::SystemTime;
+use std::time::{Duration, SystemTime};
 
 /// Return milliseconds since Unix epoch
 pub fn unix_timestamp() -> u64 {
@@ -7,3 +7,12 @@ pub fn unix_timestamp() -> u64 {
         .unwrap()
         .as_millis() as u64
 }
+
+/// Returns true if the last update, in milliseconds since Unix epoch, happened more than `max_age`
+/// before `now_ms`. Values that are never updated (no update time) are never stale.
+pub fn is_stale(updated_at_ms: Option<u64>, now_ms: u64, max_age: Duration) -> bool {
+    match updated_at_ms {
+        Some(updated_at_ms) => now_ms.saturating_sub(updated_at_ms) > max_age.as_millis() as u64,
+        None => false,
+    }
+}
@@ -1,6 +1,7 @@
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
 use alloy_primitives::Address;
@@ -9,12 +10,12 @@ use tokio::sync::watch;
 
 use super::{
     methods::{
-        api_keys::{self, APIKey},
+        api_keys::{self, rate_limit::ApiKeyTokenBucket, APIKey},
         subscriptions,
     },
     AuthToken, QuerySettings,
 };
-use crate::{http::middleware::RateLimitSettings, subscriptions::Subscription};
+use crate::{http::middleware::RateLimitSettings, subscriptions::SubscriptionsCache};
 
 #[derive(Clone)]
 pub struct AuthContext {
@@ -27,9 +28,11 @@ pub struct AuthContext {
     // Studio API keys
     pub api_keys: watch::Receiver<HashMap<String, Arc<APIKey>>>,
     pub special_api_keys: Arc<HashSet<String>>,
+    pub api_key_rate_limit: Option<Arc<ApiKeyTokenBucket>>,
 
     // Subscriptions
-    pub subscriptions: watch::Receiver<HashMap<Address, Subscription>>,
+    pub subscriptions: watch::Receiver<SubscriptionsCache>,
+    pub subscriptions_max_staleness: Duration,
     pub special_query_key_signers: Arc<HashSet<Address>>,
     pub subscription_rate_per_query: u128,
     pub subscription_domains: Arc<HashMap<u64, Address>>,
@@ -40,6 +43,7 @@ impl FromRef<AuthContext> for api_keys::AuthContext {
         Self {
             api_keys: auth.api_keys.clone(),
             special_api_keys: auth.special_api_keys.clone(),
+            rate_limit: auth.api_key_rate_limit.clone(),
         }
     }
 }
@@ -48,6 +52,7 @@ impl FromRef<AuthContext> for subscriptions::AuthContext {
     fn from_ref(auth: &AuthContext) -> Self {
         Self {
             subscriptions: auth.subscriptions.clone(),
+            max_staleness: auth.subscriptions_max_staleness,
             special_signers: auth.special_query_key_signers.clone(),
             rate_per_query: auth.subscription_rate_per_query,
             subscription_domains: auth.subscription_domains.clone(),
@@ -56,21 +61,32 @@ impl FromRef<AuthContext> for subscriptions::AuthContext {
 }
 
 impl AuthContext {
+    #[allow(clippy::too_many_arguments)]
     pub fn create(
         payment_required: bool,
         api_keys: watch::Receiver<HashMap<String, Arc<APIKey>>>,
         special_api_keys: HashSet<String>,
-        subscriptions: watch::Receiver<HashMap<Address, Subscription>>,
+        api_key_rate_limit: Option<ApiKeyTokenBucket>,
+        subscriptions: watch::Receiver<SubscriptionsCache>,
+        subscriptions_max_staleness: Duration,
         special_query_key_signers: HashSet<Address>,
         subscription_rate_per_query: u128,
         subscription_domains: HashMap<u64, Address>,
     ) -> Self {
+        // Evict the idle API key rate limit buckets every minute
+        let api_key_rate_limit = api_key_rate_limit.map(Arc::new);
+        if let Some(rate_limit) = &api_key_rate_limit {
+            rate_limit.spawn_idle_eviction(Duration::from_secs(60));
+        }
+
         Self {
             payment_required,
             api_keys,
             special_api_keys: Arc::new(special_api_keys),
+            api_key_rate_limit,
             special_query_key_signers: Arc::new(special_query_key_signers),
             subscriptions,
+            subscriptions_max_staleness,
             subscription_rate_per_query,
             subscription_domains: Arc::new(subscription_domains),
         }
@@ -102,6 +118,17 @@ impl AuthContext {
         )
     }
 
+    pub fn check_rate_limit(&self, token: &AuthToken) -> anyhow::Result<()> {
+        match token {
+            AuthToken::ApiKey(auth) => {
+                let ctx = api_keys::AuthContext::from_ref(self);
+                api_keys::check_rate_limit(&ctx, auth)
+            }
+            // Subscriptions are rate limited by the rate limiter middleware
+            AuthToken::SubscriptionsAuthToken(_) => Ok(()),
+        }
+    }
+
     pub fn check_auth_requirements(&self, token: &AuthToken) -> anyhow::Result<()> {
         match token {
             AuthToken::ApiKey(auth) => {
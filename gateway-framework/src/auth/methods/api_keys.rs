@@ -10,9 +10,12 @@ use serde_with::serde_as;
 use thegraph_core::types::SubgraphId;
 use tokio::sync::watch;
 
+use self::rate_limit::ApiKeyTokenBucket;
 use super::common;
 use crate::{auth::QuerySettings, http::middleware::RateLimitSettings};
 
+pub mod rate_limit;
+
 // TODO: This type MUST NOT implement the `Deserialize` trait.
 //   Decouple the API keys fetch types from the API keys types.
 #[serde_as]
@@ -127,6 +130,9 @@ pub struct AuthContext {
     /// An API key is considered special when does not require payment and is
     /// not subsidized, i.e., these keys won't be rejected due to non-payment.
     pub(crate) special_api_keys: Arc<HashSet<String>>,
+
+    /// Per-API key rate limit. If not set, the API keys are not rate limited.
+    pub(crate) rate_limit: Option<Arc<ApiKeyTokenBucket>>,
 }
 
 impl AuthContext {
@@ -167,6 +173,25 @@ pub fn parse_auth_token(
     Ok((AuthToken::new(api_key.clone()), Some(query_settings), None))
 }
 
+/// Check if the API key exceeded its rate limit.
+///
+/// Special keys are not rate limited.
+pub fn check_rate_limit(ctx: &AuthContext, token: &AuthToken) -> anyhow::Result<()> {
+    let rate_limit = match &ctx.rate_limit {
+        Some(rate_limit) => rate_limit,
+        None => return Ok(()),
+    };
+
+    if ctx.is_special_key(token.api_key()) {
+        return Ok(());
+    }
+
+    if !rate_limit.check(token.key()) {
+        return Err(anyhow::anyhow!("rate limit exceeded"));
+    }
+    Ok(())
+}
+
 /// Perform API key auth token specific requirements checks.
 ///
 /// Checks performed:
@@ -244,4 +269,59 @@ mod tests {
             });
         }
     }
+
+    mod rate_limiting {
+        use std::{collections::HashSet, sync::Arc};
+
+        use tokio::sync::watch;
+
+        use super::{check_rate_limit, APIKey, ApiKeyTokenBucket, AuthContext, AuthToken};
+
+        const API_KEY: &str = "0123456789abcdef0123456789abcdef";
+
+        fn test_auth_ctx(special_api_keys: HashSet<String>) -> AuthContext {
+            AuthContext {
+                api_keys: watch::channel(Default::default()).1,
+                special_api_keys: Arc::new(special_api_keys),
+                rate_limit: Some(Arc::new(ApiKeyTokenBucket::new(0.0, 1))),
+            }
+        }
+
+        fn test_auth_token() -> AuthToken {
+            AuthToken::new(Arc::new(APIKey {
+                key: API_KEY.into(),
+                ..Default::default()
+            }))
+        }
+
+        #[test]
+        fn reject_api_key_exceeding_rate_limit() {
+            //* Given
+            let ctx = test_auth_ctx(Default::default());
+            let token = test_auth_token();
+
+            //* When
+            let within_limit = check_rate_limit(&ctx, &token);
+            let exceeding_limit = check_rate_limit(&ctx, &token);
+
+            //* Then
+            assert!(within_limit.is_ok());
+            assert!(exceeding_limit.is_err());
+        }
+
+        #[test]
+        fn special_api_key_is_not_rate_limited() {
+            //* Given
+            let ctx = test_auth_ctx(HashSet::from([API_KEY.to_string()]));
+            let token = test_auth_token();
+
+            //* When
+            let results = (0..3)
+                .map(|_| check_rate_limit(&ctx, &token).is_ok())
+                .collect::<Vec<_>>();
+
+            //* Then
+            assert_eq!(results, [true, true, true]);
+        }
+    }
 }
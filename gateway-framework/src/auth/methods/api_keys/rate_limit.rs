@@ -0,0 +1,164 @@
+//! Per-API key rate limiting.
+//!
+//! Each API key gets a token bucket that holds up to `burst` tokens and is refilled at a constant
+//! rate. Every query consumes a token, and queries are rejected while the bucket is empty.
+//!
+//! A bucket refilled up to the burst behaves like a new one, so the idle buckets are periodically
+//! evicted to keep the number of tracked API keys bounded.
+
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use dashmap::DashMap;
+use tokio::time::MissedTickBehavior;
+
+/// The token bucket of an API key.
+#[derive(Debug)]
+struct Bucket {
+    /// The available tokens.
+    tokens: f64,
+    /// The last time the bucket was refilled.
+    refilled_at: Instant,
+}
+
+/// A token bucket rate limiter, keyed by API key.
+pub struct ApiKeyTokenBucket {
+    /// The token refill rate, in tokens per second.
+    rate: f64,
+    /// The maximum number of tokens in a bucket.
+    burst: u32,
+    /// The token buckets, indexed by API key.
+    buckets: DashMap<String, Bucket>,
+}
+
+impl ApiKeyTokenBucket {
+    /// Create a new [`ApiKeyTokenBucket`] refilling `rate` tokens per second, up to `burst` tokens.
+    pub fn new(rate: f64, burst: u32) -> Self {
+        Self {
+            rate,
+            burst,
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// Consume a token of the API key's bucket.
+    ///
+    /// Returns false if the API key exceeded its rate limit, i.e., its bucket is empty.
+    pub fn check(&self, key: &str) -> bool {
+        self.check_at(key, Instant::now())
+    }
+
+    /// Remove the buckets that have been refilled up to the burst.
+    pub fn evict_idle(&self) {
+        self.evict_idle_at(Instant::now())
+    }
+
+    /// Spawn a background task evicting the idle buckets every `interval`.
+    pub fn spawn_idle_eviction(self: &Arc<Self>, interval: Duration) {
+        let limiter = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(interval);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            loop {
+                interval.tick().await;
+                limiter.evict_idle();
+            }
+        });
+    }
+
+    fn evict_idle_at(&self, now: Instant) {
+        let burst = self.burst as f64;
+        self.buckets.retain(|_, bucket| {
+            let elapsed = now.saturating_duration_since(bucket.refilled_at);
+            bucket.tokens + elapsed.as_secs_f64() * self.rate < burst
+        });
+        self.buckets.shrink_to_fit();
+    }
+
+    fn check_at(&self, key: &str, now: Instant) -> bool {
+        let burst = self.burst as f64;
+        let mut bucket = self.buckets.entry(key.to_string()).or_insert(Bucket {
+            tokens: burst,
+            refilled_at: now,
+        });
+
+        // Refill the bucket with the tokens accrued since the last refill
+        let elapsed = now.saturating_duration_since(bucket.refilled_at);
+        bucket.tokens = (bucket.tokens + elapsed.as_secs_f64() * self.rate).min(burst);
+        bucket.refilled_at = now;
+
+        if bucket.tokens < 1.0 {
+            return false;
+        }
+        bucket.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::ApiKeyTokenBucket;
+
+    #[test]
+    fn reject_key_exceeding_burst() {
+        //* Given
+        let limiter = ApiKeyTokenBucket::new(1.0, 3);
+        let now = Instant::now();
+
+        //* When
+        let within_burst = (0..3)
+            .map(|_| limiter.check_at("key", now))
+            .collect::<Vec<_>>();
+        let exceeding_burst = limiter.check_at("key", now);
+        let other_key = limiter.check_at("other-key", now);
+
+        //* Then
+        assert_eq!(within_burst, [true, true, true]);
+        assert!(!exceeding_burst);
+        assert!(other_key);
+    }
+
+    #[test]
+    fn key_recovers_after_refill() {
+        //* Given
+        let limiter = ApiKeyTokenBucket::new(2.0, 2);
+        let now = Instant::now();
+        assert!(limiter.check_at("key", now));
+        assert!(limiter.check_at("key", now));
+        assert!(!limiter.check_at("key", now));
+
+        //* When
+        let before_refill = limiter.check_at("key", now + Duration::from_millis(400));
+        let after_refill = limiter.check_at("key", now + Duration::from_millis(600));
+        let after_long_idle = (0..3)
+            .map(|_| limiter.check_at("key", now + Duration::from_secs(60)))
+            .collect::<Vec<_>>();
+
+        //* Then
+        assert!(!before_refill);
+        assert!(after_refill);
+        // The bucket never holds more than the burst
+        assert_eq!(after_long_idle, [true, true, false]);
+    }
+
+    #[test]
+    fn idle_buckets_are_evicted() {
+        //* Given
+        let limiter = ApiKeyTokenBucket::new(1.0, 2);
+        let now = Instant::now();
+        assert!(limiter.check_at("idle-key", now));
+        assert!(limiter.check_at("busy-key", now));
+        assert!(limiter.check_at("busy-key", now + Duration::from_millis(500)));
+
+        //* When
+        limiter.evict_idle_at(now + Duration::from_secs(1));
+
+        //* Then
+        assert!(!limiter.buckets.contains_key("idle-key"));
+        assert!(limiter.buckets.contains_key("busy-key"));
+    }
+}
@@ -1,9 +1,11 @@
 use std::{
     collections::{HashMap, HashSet},
     sync::Arc,
+    time::Duration,
 };
 
 use alloy_primitives::Address;
+use gateway_common::utils::timestamp::unix_timestamp;
 use thegraph_core::{
     subscriptions::auth::{
         parse_auth_token as parse_bearer_token, verify_auth_token_claims, AuthTokenClaims,
@@ -14,7 +16,9 @@ use tokio::sync::watch;
 
 use super::common;
 use crate::{
-    auth::QuerySettings, http::middleware::RateLimitSettings, subscriptions::Subscription,
+    auth::QuerySettings,
+    http::middleware::RateLimitSettings,
+    subscriptions::{Subscription, SubscriptionsCache},
 };
 
 /// Auth token wrapper around the Subscriptions auth token claims and the subscription.
@@ -77,12 +81,14 @@ impl std::fmt::Display for AuthToken {
 
 /// App state (a.k.a [Context](crate::client_query::Context)) sub-state.
 pub struct AuthContext {
-    /// A map between the Subscription's user address and the actual
-    /// active subscription.
+    /// The last-known active subscriptions, by user address.
     ///
     /// Subscriptions are fetched periodically (every 30s) from the Subscriptions subgraph by
     /// the gateway using the [`subscriptions_subgraph` client](crate::subscriptions_subgraph::Client).
-    pub(crate) subscriptions: watch::Receiver<HashMap<Address, Subscription>>,
+    pub(crate) subscriptions: watch::Receiver<SubscriptionsCache>,
+
+    /// Maximum age of the subscriptions before they are no longer served.
+    pub(crate) max_staleness: Duration,
 
     /// Auth token signers that don't require payment.
     pub(crate) special_signers: Arc<HashSet<Address>>,
@@ -96,8 +102,18 @@ pub struct AuthContext {
 
 impl AuthContext {
     /// Get the subscription associated with the auth token claims user.
-    pub fn get_subscription_for_user(&self, user: &Address) -> Option<Subscription> {
-        self.subscriptions.borrow().get(user).cloned()
+    ///
+    /// Returns an error if the subscriptions are older than the maximum staleness, e.g., because
+    /// the subscriptions subgraph is unreachable.
+    pub fn get_subscription_for_user(
+        &self,
+        user: &Address,
+    ) -> anyhow::Result<Option<Subscription>> {
+        let cache = self.subscriptions.borrow();
+        if cache.is_stale(unix_timestamp(), self.max_staleness) {
+            return Err(anyhow::anyhow!("subscription data stale"));
+        }
+        Ok(cache.subscriptions.get(user).cloned())
     }
 
     /// Returns `true` if the given address corresponds to a special signer.
@@ -140,7 +156,7 @@ pub fn parse_auth_token(
 
     // Retrieve the subscription associated with the auth token user
     let subscription = ctx
-        .get_subscription_for_user(&user)
+        .get_subscription_for_user(&user)?
         .ok_or_else(|| anyhow::anyhow!("subscription not found for user {}", user))?;
 
     // Calculate the expected queries per minute rate
@@ -207,3 +223,67 @@ pub fn check_auth_requirements(ctx: &AuthContext, token: &AuthToken) -> anyhow::
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc, time::Duration};
+
+    use alloy_primitives::Address;
+    use gateway_common::utils::timestamp::unix_timestamp;
+    use tokio::sync::watch;
+
+    use super::AuthContext;
+    use crate::subscriptions::{Subscription, SubscriptionsCache};
+
+    /// Create a test authorization context holding the subscription of the given user, last
+    /// updated the given time ago.
+    fn test_auth_ctx(user: Address, updated_ago: Duration) -> AuthContext {
+        let cache = SubscriptionsCache {
+            subscriptions: HashMap::from([(
+                user,
+                Subscription {
+                    signers: vec![user],
+                    rate: 100,
+                },
+            )]),
+            updated_at_ms: Some(unix_timestamp() - updated_ago.as_millis() as u64),
+        };
+        AuthContext {
+            subscriptions: watch::channel(cache).1,
+            max_staleness: Duration::from_secs(600),
+            special_signers: Arc::new(Default::default()),
+            rate_per_query: 1,
+            subscription_domains: Arc::new(Default::default()),
+        }
+    }
+
+    #[test]
+    fn serve_last_known_subscriptions_within_max_staleness() {
+        //* Given
+        let user = Address::repeat_byte(0x01);
+        // The last successful fetch was 5 minutes ago, later fetches failed
+        let ctx = test_auth_ctx(user, Duration::from_secs(300));
+
+        //* When
+        let subscription = ctx.get_subscription_for_user(&user);
+
+        //* Then
+        let subscription = subscription.expect("subscriptions are not stale");
+        assert_eq!(subscription.map(|s| s.rate), Some(100));
+    }
+
+    #[test]
+    fn reject_subscriptions_beyond_max_staleness() {
+        //* Given
+        let user = Address::repeat_byte(0x01);
+        // The last successful fetch was 20 minutes ago, later fetches failed
+        let ctx = test_auth_ctx(user, Duration::from_secs(1200));
+
+        //* When
+        let subscription = ctx.get_subscription_for_user(&user);
+
+        //* Then
+        let err = subscription.expect_err("subscriptions are stale");
+        assert_eq!(err.to_string(), "subscription data stale");
+    }
+}
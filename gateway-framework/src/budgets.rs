@@ -1,6 +1,7 @@
 use std::time::Duration;
 
 use ordered_float::NotNan;
+use serde::Deserialize;
 use tokio::{
     select, spawn,
     sync::{mpsc, watch},
@@ -12,6 +13,29 @@ use crate::reporting::METRICS;
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
 pub struct USD(pub NotNan<f64>);
 
+/// The rounding applied to fractional indexer fees, in GRT wei, before signing receipts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeeRounding {
+    /// Round down, never paying more than the fee.
+    #[default]
+    Floor,
+    /// Round up, never paying less than the fee.
+    Ceil,
+    /// Round to the nearest wei, with ties rounding away from zero.
+    Nearest,
+}
+
+/// Convert the fee, in GRT wei, to the whole amount of GRT wei to sign in the receipt.
+pub fn round_fee(fee: NotNan<f64>, rounding: FeeRounding) -> u128 {
+    let fee = match rounding {
+        FeeRounding::Floor => fee.floor(),
+        FeeRounding::Ceil => fee.ceil(),
+        FeeRounding::Nearest => fee.round(),
+    };
+    fee as u128
+}
+
 pub struct Budgeter {
     pub feedback: mpsc::UnboundedSender<USD>,
     pub query_fees_target: USD,
@@ -182,3 +206,36 @@ where
         self.frames[0] = T::default();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use ordered_float::NotNan;
+
+    use super::{round_fee, FeeRounding};
+
+    #[test]
+    fn round_fractional_fee() {
+        //* Given
+        let fee = NotNan::new(1_000.4).unwrap();
+        let fee_half = NotNan::new(1_000.5).unwrap();
+
+        //* Then
+        assert_eq!(round_fee(fee, FeeRounding::Floor), 1_000);
+        assert_eq!(round_fee(fee, FeeRounding::Ceil), 1_001);
+        assert_eq!(round_fee(fee, FeeRounding::Nearest), 1_000);
+        assert_eq!(round_fee(fee_half, FeeRounding::Floor), 1_000);
+        assert_eq!(round_fee(fee_half, FeeRounding::Ceil), 1_001);
+        assert_eq!(round_fee(fee_half, FeeRounding::Nearest), 1_001);
+    }
+
+    #[test]
+    fn round_whole_fee() {
+        //* Given
+        let fee = NotNan::new(1_000.0).unwrap();
+
+        //* Then
+        assert_eq!(round_fee(fee, FeeRounding::Floor), 1_000);
+        assert_eq!(round_fee(fee, FeeRounding::Ceil), 1_000);
+        assert_eq!(round_fee(fee, FeeRounding::Nearest), 1_000);
+    }
+}
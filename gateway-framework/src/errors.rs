@@ -51,6 +51,28 @@ pub enum IndexerError {
     /// The indexer’s response is bad.
     #[error("BadResponse({0:#})")]
     BadResponse(String),
+    /// The indexer’s response is missing a required attestation.
+    #[error("NoAttestation")]
+    NoAttestation,
+    /// The indexer’s response payload is not valid JSON, or does not have the expected shape.
+    #[error("UnexpectedPayload({0})")]
+    UnexpectedPayload(String),
+}
+
+impl IndexerError {
+    /// A stable, low-cardinality label of the error kind, suitable for metric dimensions.
+    ///
+    /// Unlike the `Display` output, it does not include the error details.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Internal(_) => "internal",
+            Self::Unavailable(_) => "unavailable",
+            Self::Timeout => "timeout",
+            Self::BadResponse(_) => "bad_response",
+            Self::NoAttestation => "no_attestation",
+            Self::UnexpectedPayload(_) => "unexpected_payload",
+        }
+    }
 }
 
 #[derive(thiserror::Error, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -67,4 +89,55 @@ pub enum UnavailableReason {
     /// The indexer did not have a block required by the query.
     #[error("missing block")]
     MissingBlock,
+    /// The indexer rejected the receipt because it has no valid allocation for the deployment.
+    #[error("no allocation")]
+    NoAllocation,
+    /// Failed to connect to the indexer.
+    #[error("unreachable")]
+    Unreachable,
+    /// The indexer responded with a server error (HTTP 5xx).
+    #[error("server error")]
+    ServerError,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::{IndexerError, UnavailableReason};
+
+    #[test]
+    fn indexer_error_labels_are_distinct_and_stable() {
+        //* Given
+        let errors = [
+            IndexerError::Internal("internal error"),
+            IndexerError::Unavailable(UnavailableReason::NoStatus),
+            IndexerError::Timeout,
+            IndexerError::BadResponse("502".to_string()),
+            IndexerError::NoAttestation,
+            IndexerError::UnexpectedPayload("expected value at line 1".to_string()),
+        ];
+
+        //* When
+        let labels = errors.iter().map(IndexerError::label).collect::<Vec<_>>();
+
+        //* Then
+        assert_eq!(
+            labels,
+            [
+                "internal",
+                "unavailable",
+                "timeout",
+                "bad_response",
+                "no_attestation",
+                "unexpected_payload",
+            ]
+        );
+        assert_eq!(labels.iter().collect::<HashSet<_>>().len(), errors.len());
+        // The label does not depend on the error details
+        assert_eq!(
+            IndexerError::BadResponse("failed to connect".to_string()).label(),
+            "bad_response"
+        );
+    }
 }
@@ -6,6 +6,6 @@ mod require_auth;
 
 pub use legacy_auth::legacy_auth_adapter;
 pub use rate_limiter::{AddRateLimiterLayer, RateLimitSettings, RateLimiter};
-pub use request_id::{SetRequestId, SetRequestIdLayer};
+pub use request_id::{QueryId, SetRequestId, SetRequestIdLayer};
 pub use request_tracing::{RequestTracing, RequestTracingLayer};
 pub use require_auth::{RequireAuthorization, RequireAuthorizationLayer};
@@ -1,6 +1,7 @@
 use std::{
-    sync::{atomic, atomic::AtomicU64, Arc},
+    sync::{atomic, atomic::AtomicU64, Arc, Mutex},
     task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
 use axum::http::{HeaderName, HeaderValue, Request, Response};
@@ -51,12 +52,73 @@ impl std::fmt::Debug for RequestId {
     }
 }
 
+/// A unique, time-sortable identifier for a client query.
+///
+/// The ID follows the [ULID](https://github.com/ulid/spec) layout: a 48-bit Unix timestamp in
+/// milliseconds followed by 80 random bits, encoded as 26 Crockford base32 characters. IDs
+/// generated by the same process are strictly increasing, even within the same millisecond.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct QueryId(u128);
+
+impl QueryId {
+    /// Generate a new [`QueryId`].
+    pub fn new() -> Self {
+        /// The last generated ID, used to guarantee monotonicity.
+        static LAST: Mutex<u128> = Mutex::new(0);
+
+        const RANDOM_BITS: u32 = 80;
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis()
+            & ((1 << 48) - 1);
+        let random = rand::random::<u128>() & ((1 << RANDOM_BITS) - 1);
+        let id = (timestamp_ms << RANDOM_BITS) | random;
+
+        let mut last = LAST.lock().unwrap();
+        let id = if id > *last { id } else { *last + 1 };
+        *last = id;
+        Self(id)
+    }
+
+    /// The Unix timestamp, in milliseconds, at which the ID was generated.
+    pub fn timestamp_ms(&self) -> u64 {
+        (self.0 >> 80) as u64
+    }
+}
+
+impl Default for QueryId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for QueryId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+        let mut encoded = [0_u8; 26];
+        for (i, c) in encoded.iter_mut().rev().enumerate() {
+            *c = ALPHABET[((self.0 >> (5 * i)) & 0x1f) as usize];
+        }
+        // The alphabet is ASCII, so the encoded ID is valid UTF-8
+        f.write_str(std::str::from_utf8(&encoded).unwrap())
+    }
+}
+
+impl std::fmt::Debug for QueryId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(self, f)
+    }
+}
+
 /// Set request IDs on ingoing requests.
 ///
 /// If the request has a `cf-ray` header, it will be used as the request ID. Otherwise, a new request ID
 /// derived from the gateway ID and a counter will be used.
 ///
-/// The middleware inserts the request ID into the request extensions.
+/// Additionally, a new [`QueryId`] is generated for every request.
+///
+/// The middleware inserts the request ID and the query ID into the request extensions.
 #[derive(Clone, Debug)]
 pub struct SetRequestId<S> {
     inner: S,
@@ -89,6 +151,8 @@ where
     }
 
     fn call(&mut self, mut req: Request<ReqBody>) -> Self::Future {
+        tracing::span::Span::current().record("gateway_id", self.gateway_id.as_str());
+
         if req.extensions().get::<RequestId>().is_none() {
             let request_id = if let Some(ray_id) = req.headers().get(&CLOUDFLARE_RAY_ID) {
                 RequestId::from_header_value(ray_id)
@@ -106,6 +170,12 @@ where
             req.extensions_mut().insert(request_id);
         }
 
+        if req.extensions().get::<QueryId>().is_none() {
+            let query_id = QueryId::new();
+            tracing::span::Span::current().record("query_id", tracing::field::display(&query_id));
+            req.extensions_mut().insert(query_id);
+        }
+
         self.inner.call(req)
     }
 }
@@ -142,7 +212,7 @@ mod tests {
     use hyper::http;
     use tower::{Service, ServiceBuilder, ServiceExt};
 
-    use super::{RequestId, SetRequestIdLayer};
+    use super::{QueryId, RequestId, SetRequestIdLayer};
 
     #[tokio::test]
     async fn cf_ray_header_is_present() {
@@ -256,4 +326,23 @@ mod tests {
             expected_request_id
         );
     }
+
+    #[test]
+    fn query_ids_are_unique_and_sortable() {
+        //* When
+        let ids = (0..10_000).map(|_| QueryId::new()).collect::<Vec<_>>();
+
+        //* Then
+        let unique = ids.iter().collect::<std::collections::HashSet<_>>();
+        assert_eq!(unique.len(), ids.len());
+
+        // The IDs, and their string representations, sort in creation order
+        let encoded = ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert!(ids.windows(2).all(|w| w[0] < w[1]));
+        assert!(encoded.windows(2).all(|w| w[0] < w[1]));
+        assert!(ids
+            .windows(2)
+            .all(|w| w[0].timestamp_ms() <= w[1].timestamp_ms()));
+        assert!(encoded.iter().all(|id| id.len() == 26));
+    }
 }
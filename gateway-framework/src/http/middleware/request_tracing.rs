@@ -17,7 +17,9 @@ const REQUEST_SPAN_TARGET: &str = "client_request";
 ///  - Name: `client request`
 ///  - Fields:
 ///    - `request_id`: The ID of the request
+///    - `query_id`: The unique, time-sortable ID of the query
 ///    - `graph_env`: The graph environment of the request (e.g. `mainnet`, `testnet`, etc.)
+///    - `gateway_id`: The ID of the gateway handling the request
 ///    - `selector`: The request selector (e.g. Subgraph DeploymentId or SubgraphId )
 ///
 /// **Important**: This middleware should be used as the first layer in the request handling middleware stack.
@@ -56,7 +58,9 @@ where
             target: REQUEST_SPAN_TARGET,
             "client request",  // name
             graph_env = %self.env_id,
+            gateway_id = field::Empty,
             request_id = field::Empty,
+            query_id = field::Empty,
             selector = field::Empty,
         )
         .entered();
@@ -7,6 +7,7 @@ use std::{
 };
 
 use axum::http::Request;
+use gateway_common::utils::timestamp::unix_timestamp;
 use headers::{authorization::Bearer, Authorization, HeaderMapExt, Origin};
 use tower::Service;
 
@@ -14,7 +15,7 @@ use crate::{
     auth::{AuthContext, AuthToken},
     errors::Error,
     graphql,
-    reporting::CLIENT_REQUEST_TARGET,
+    reporting::{legacy_error_status, CLIENT_REQUEST_TARGET},
 };
 
 #[pin_project::pin_project(project = KindProj)]
@@ -117,14 +118,14 @@ where
             Ok(Some(Authorization(bearer))) => bearer,
             Ok(None) => {
                 // If the `Authorization` header is not present, return an error response
-                return ResponseFuture::error(graphql::error_response(Error::Auth(
-                    anyhow::anyhow!("missing authorization header"),
+                return ResponseFuture::error(auth_error_response(anyhow::anyhow!(
+                    "missing authorization header"
                 )));
             }
             Err(_) => {
                 // If the `Authorization` header is invalid, return an error response
-                return ResponseFuture::error(graphql::error_response(Error::Auth(
-                    anyhow::anyhow!("invalid authorization header"),
+                return ResponseFuture::error(auth_error_response(anyhow::anyhow!(
+                    "invalid authorization header"
                 )));
             }
         };
@@ -135,8 +136,8 @@ where
                 Ok(token) => token,
                 Err(err) => {
                     // If the bearer token is invalid, return an error response
-                    return ResponseFuture::error(graphql::error_response(Error::Auth(
-                        anyhow::anyhow!("invalid bearer token: {err}"),
+                    return ResponseFuture::error(auth_error_response(anyhow::anyhow!(
+                        "invalid bearer token: {err}"
                     )));
                 }
             };
@@ -159,15 +160,20 @@ where
 
         if !auth_token.is_domain_authorized(origin.hostname()) {
             // If the request origin domain is not allowed, return an error response
-            return ResponseFuture::error(graphql::error_response(Error::Auth(anyhow::anyhow!(
+            return ResponseFuture::error(auth_error_response(anyhow::anyhow!(
                 "domain not authorized by user"
-            ))));
+            )));
+        }
+
+        // Check the auth token rate limit, whether payment is required or not
+        if let Err(err) = self.ctx.check_rate_limit(&auth_token) {
+            return ResponseFuture::error(auth_error_response(err));
         }
 
         // If payment is required, check the auth schema specific requirements
         if self.ctx.payment_required {
             if let Err(err) = self.ctx.check_auth_requirements(&auth_token) {
-                return ResponseFuture::error(graphql::error_response(Error::Auth(err)));
+                return ResponseFuture::error(auth_error_response(err));
             }
         }
 
@@ -188,6 +194,24 @@ where
     }
 }
 
+/// Reports the client request as failed with an authorization error, and returns the GraphQL
+/// error response.
+///
+/// Requests rejected here never reach the query handler, so this is the only client request
+/// report they produce.
+fn auth_error_response(err: anyhow::Error) -> axum::http::Response<String> {
+    let err = Error::Auth(err);
+    let (legacy_status_message, legacy_status_code) = legacy_error_status(&err);
+    tracing::info!(
+        target: CLIENT_REQUEST_TARGET,
+        start_time_ms = unix_timestamp(),
+        status_message = %err,
+        %legacy_status_message,
+        legacy_status_code,
+    );
+    graphql::error_response(err)
+}
+
 /// A layer that applies [`RequireAuthorization`] which requires the requests to be authorized.
 ///
 /// See [`RequireAuthorization`] for more details.
@@ -213,7 +237,10 @@ impl<S> tower::layer::Layer<S> for RequireAuthorizationLayer {
 
 #[cfg(test)]
 mod tests {
-    use std::{collections::HashMap, sync::Arc};
+    use std::{
+        collections::HashMap,
+        sync::{Arc, Mutex},
+    };
 
     use assert_matches::assert_matches;
     use axum::body::Body;
@@ -223,17 +250,26 @@ mod tests {
     use ordered_float::NotNan;
     use tokio::sync::watch;
     use tokio_test::assert_ready_ok;
+    use tracing_subscriber::layer::SubscriberExt as _;
 
     use super::{AuthContext, AuthToken, RequireAuthorizationLayer};
-    use crate::auth::{methods::api_keys, QuerySettings};
+    use crate::{
+        auth::{
+            methods::api_keys::{self, rate_limit::ApiKeyTokenBucket},
+            QuerySettings,
+        },
+        reporting::CLIENT_REQUEST_TARGET,
+    };
 
     fn test_auth_ctx(key: Option<&str>) -> AuthContext {
         let mut ctx = AuthContext {
             payment_required: false,
             api_keys: watch::channel(Default::default()).1,
             special_api_keys: Default::default(),
+            api_key_rate_limit: None,
             special_query_key_signers: Default::default(),
             subscriptions: watch::channel(Default::default()).1,
+            subscriptions_max_staleness: Default::default(),
             subscription_rate_per_query: 0,
             subscription_domains: Default::default(),
         };
@@ -525,4 +561,104 @@ mod tests {
         });
         assert_matches!(r.extensions().get::<QuerySettings>(), Some(_));
     }
+
+    /// API keys exceeding their rate limit should be rejected, even if payment is not required.
+    #[tokio::test]
+    async fn api_key_exceeding_rate_limit_is_rejected() {
+        //* Given
+        let api_key = "0123456789abcdef0123456789abcdef";
+
+        let mut auth_ctx = test_auth_ctx(Some(api_key));
+        auth_ctx.payment_required = false;
+        auth_ctx.api_key_rate_limit = Some(Arc::new(ApiKeyTokenBucket::new(0.0, 1)));
+
+        let (mut svc, mut handle) =
+            tower_test::mock::spawn_layer(RequireAuthorizationLayer::new(auth_ctx));
+
+        //* When
+        // The service must be ready before calling it
+        handle.allow(2);
+
+        // The first request uses up the burst
+        assert_ready_ok!(svc.poll_ready());
+        svc.call(test_req_with_auth_header(api_key));
+        handle
+            .next_request()
+            .await
+            .expect("service received a request");
+
+        // Call the wrapped service and await the response
+        assert_ready_ok!(svc.poll_ready());
+        let res = svc.call(test_req_with_auth_header(api_key)).await;
+
+        //* Then
+        assert_matches!(res, Ok(mut res) => {
+            assert_eq!(res.status(), http::StatusCode::OK);
+            assert_matches!(deserialize_graphql_response_body::<()>(res.body_mut()).await, Ok(res_body) => {
+                assert_eq!(res_body.errors.len(), 1);
+                assert_eq!(res_body.errors[0].message, "auth error: rate limit exceeded");
+            });
+        });
+    }
+
+    /// Tracing layer collecting the fields of the client request events.
+    #[derive(Clone, Default)]
+    struct ClientRequestEvents(Arc<Mutex<Vec<HashMap<String, String>>>>);
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for ClientRequestEvents {
+        fn on_event(
+            &self,
+            event: &tracing::Event<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if event.metadata().target() != CLIENT_REQUEST_TARGET {
+                return;
+            }
+
+            let mut fields = EventFields::default();
+            event.record(&mut fields);
+            self.0.lock().unwrap().push(fields.0);
+        }
+    }
+
+    #[derive(Default)]
+    struct EventFields(HashMap<String, String>);
+
+    impl tracing::field::Visit for EventFields {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+    }
+
+    /// If the request is rejected, the middleware should report the client request.
+    #[tokio::test]
+    async fn rejected_request_is_reported() {
+        //* Given
+        let auth_ctx = test_auth_ctx(None);
+
+        let (mut svc, mut handle) =
+            tower_test::mock::spawn_layer(RequireAuthorizationLayer::new(auth_ctx));
+
+        let req = test_req_with_auth_header("0123456789abcdef0123456789abcdef");
+
+        let events = ClientRequestEvents::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(events.clone()));
+
+        //* When
+        // The service must be ready before calling it
+        handle.allow(1);
+        assert_ready_ok!(svc.poll_ready());
+
+        // Call the wrapped service and await the response
+        let res = svc.call(req).await;
+
+        //* Then
+        assert_matches!(res, Ok(_));
+        let events = events.0.lock().unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0]["legacy_status_message"], "Invalid API key");
+        assert_eq!(events[0]["legacy_status_code"], "888904173");
+    }
 }
@@ -13,5 +13,7 @@ pub mod network;
 pub mod reporting;
 pub mod scalar;
 pub mod subscriptions;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod test_utils;
 pub mod topology;
 pub mod utils;
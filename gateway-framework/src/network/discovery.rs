@@ -2,6 +2,8 @@ use alloy_primitives::BlockNumber;
 use cost_model::CostModel;
 use eventuals::Ptr;
 
+use crate::scalar::ReceiptVersion;
+
 #[derive(Clone)]
 pub struct Status {
     pub block: BlockNumber,
@@ -9,3 +11,17 @@ pub struct Status {
     pub cost_model: Option<Ptr<CostModel>>,
     pub legacy_scalar: bool,
 }
+
+impl Status {
+    /// The receipt version to use when paying the indexer for queries to the indexing.
+    ///
+    /// Indexers supporting Scalar TAP are paid with TAP receipts, otherwise legacy receipts are
+    /// used.
+    pub fn receipt_version(&self) -> ReceiptVersion {
+        if self.legacy_scalar {
+            ReceiptVersion::Legacy
+        } else {
+            ReceiptVersion::TAP
+        }
+    }
+}
@@ -6,6 +6,7 @@ use ethers::{
     prelude::{abigen, Http},
     providers::Provider,
 };
+use gateway_common::utils::timestamp::{self, unix_timestamp};
 use ordered_float::NotNan;
 use tokio::{
     sync::watch,
@@ -19,7 +20,33 @@ abigen!(
     event_derives(serde::Deserialize, serde::Serialize);
 );
 
-pub async fn grt_per_usd(provider: Url) -> anyhow::Result<watch::Receiver<NotNan<f64>>> {
+/// Default maximum age of an exchange rate before it is considered stale.
+pub const DEFAULT_MAX_AGE: Duration = Duration::from_secs(300);
+
+/// A GRT/USD exchange rate, and the time it was last updated.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GrtPerUsd {
+    pub rate: NotNan<f64>,
+    /// Unix timestamp, in milliseconds, of the last update. Fixed rates have no update time.
+    pub updated_at_ms: Option<u64>,
+}
+
+impl GrtPerUsd {
+    /// A fixed exchange rate, which is never stale.
+    pub fn fixed(rate: NotNan<f64>) -> Self {
+        Self {
+            rate,
+            updated_at_ms: None,
+        }
+    }
+
+    /// Returns true if the rate was last updated more than `max_age` before `now_ms`.
+    pub fn is_stale(&self, now_ms: u64, max_age: Duration) -> bool {
+        timestamp::is_stale(self.updated_at_ms, now_ms, max_age)
+    }
+}
+
+pub async fn grt_per_usd(provider: Url) -> anyhow::Result<watch::Receiver<GrtPerUsd>> {
     // https://data.chain.link/ethereum/mainnet/crypto-eth/grt-eth
     let chainlink_eth_per_grt: Address = "0x17d054ecac33d91f7340645341efb5de9009f1c1"
         .parse()
@@ -37,7 +64,7 @@ pub async fn grt_per_usd(provider: Url) -> anyhow::Result<watch::Receiver<NotNan
         ChainlinkPriceFeed::new(chainlink_usd_per_eth, provider),
     ));
 
-    let (tx, mut rx) = watch::channel(NotNan::new(0.0).unwrap());
+    let (tx, mut rx) = watch::channel(GrtPerUsd::fixed(NotNan::new(0.0).unwrap()));
     tokio::spawn(async move {
         let mut interval = interval(Duration::from_secs(60));
         interval.set_missed_tick_behavior(MissedTickBehavior::Skip);
@@ -60,13 +87,17 @@ pub async fn grt_per_usd(provider: Url) -> anyhow::Result<watch::Receiver<NotNan
             };
             let grt_per_usd = NotNan::new((eth_per_grt * usd_per_eth).recip()).unwrap();
             tracing::info!(%grt_per_usd);
-            if let Err(grt_per_usd_send_err) = tx.send(grt_per_usd) {
+            let update = GrtPerUsd {
+                rate: grt_per_usd,
+                updated_at_ms: Some(unix_timestamp()),
+            };
+            if let Err(grt_per_usd_send_err) = tx.send(update) {
                 tracing::error!(%grt_per_usd_send_err);
             }
         }
     });
 
-    let _ = rx.wait_for(|v| *v != 0.0).await;
+    let _ = rx.wait_for(|v| *v.rate != 0.0).await;
     Ok(rx)
 }
 
@@ -79,3 +110,28 @@ async fn fetch_price(contract: &ChainlinkPriceFeed<Provider<Http>>) -> anyhow::R
         latest_answer as f64 * 10.0_f64.powi(-(decimals as i32)),
     )?)
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ordered_float::NotNan;
+
+    use super::GrtPerUsd;
+
+    #[test]
+    fn exchange_rate_staleness() {
+        //* Given
+        let rate = NotNan::new(10.0).unwrap();
+        let max_age = Duration::from_secs(300);
+        let updated = GrtPerUsd {
+            rate,
+            updated_at_ms: Some(1_000_000),
+        };
+
+        //* Then
+        assert!(!updated.is_stale(1_000_000 + 300_000, max_age));
+        assert!(updated.is_stale(1_000_000 + 300_001, max_age));
+        assert!(!GrtPerUsd::fixed(rate).is_stale(u64::MAX, max_age));
+    }
+}
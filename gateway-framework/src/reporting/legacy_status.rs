@@ -0,0 +1,28 @@
+use crate::errors::Error;
+
+/// The legacy status message and code reported for a client request result.
+pub fn legacy_status<T>(result: &Result<T, Error>) -> (String, u32) {
+    match result {
+        Ok(_) => ("200 OK".to_string(), 0),
+        Err(err) => legacy_error_status(err),
+    }
+}
+
+/// The legacy status message and code reported for a failed client request.
+pub fn legacy_error_status(err: &Error) -> (String, u32) {
+    match err {
+        Error::BlockNotFound(_) => ("Unresolved block".to_string(), 604610595),
+        Error::Internal(_) => ("Internal error".to_string(), 816601499),
+        Error::Auth(_) => ("Invalid API key".to_string(), 888904173),
+        Error::BadQuery(_) => ("Invalid query".to_string(), 595700117),
+        Error::NoIndexers => (
+            "No indexers found for subgraph deployment".to_string(),
+            1621366907,
+        ),
+        Error::BadIndexers(_) => (
+            "No suitable indexer found for subgraph deployment".to_string(),
+            510359393,
+        ),
+        Error::SubgraphNotFound(_) => (err.to_string(), 2599148187),
+    }
+}
@@ -18,6 +18,8 @@ pub struct Metrics {
     pub partial_voucher: ResponseMetrics,
     pub voucher: ResponseMetrics,
     pub blocks_per_minute: IntGaugeVec,
+    pub attestations: AttestationMetrics,
+    pub indexer_client: IndexerClientMetrics,
 }
 
 impl Metrics {
@@ -50,6 +52,76 @@ impl Metrics {
                 &["chain"]
             )
             .unwrap(),
+            attestations: AttestationMetrics::new(),
+            indexer_client: IndexerClientMetrics::new(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct AttestationMetrics {
+    /// Attestations received from indexers.
+    pub seen: IntCounter,
+    /// Attestations sent to the attestations topic.
+    pub reported: IntCounter,
+    /// Attestations not reported, because they failed verification.
+    pub dropped_invalid: IntCounter,
+    /// Request or response payloads omitted from reported attestations, because they exceed the
+    /// payload size limit.
+    pub dropped_payloads: IntCounter,
+}
+
+impl AttestationMetrics {
+    fn new() -> Self {
+        Self {
+            seen: register_int_counter!("gw_attestations_seen", "attestations received").unwrap(),
+            reported: register_int_counter!("gw_attestations_reported", "attestations reported")
+                .unwrap(),
+            dropped_invalid: register_int_counter!(
+                "gw_attestations_dropped_invalid",
+                "attestations dropped due to failed verification"
+            )
+            .unwrap(),
+            dropped_payloads: register_int_counter!(
+                "gw_attestation_payloads_dropped",
+                "attestation payloads dropped due to size"
+            )
+            .unwrap(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct IndexerClientMetrics {
+    /// Indexer response time, by indexer.
+    pub duration: HistogramVec,
+    /// Indexer responses, by indexer and HTTP status class (e.g. `2xx`).
+    pub status: IntCounterVec,
+    /// Indexer query errors, by indexer and error kind.
+    pub errors: IntCounterVec,
+}
+
+impl IndexerClientMetrics {
+    fn new() -> Self {
+        Self {
+            duration: register_histogram_vec!(
+                "gw_indexer_client_duration",
+                "indexer client response time",
+                &["indexer"]
+            )
+            .unwrap(),
+            status: register_int_counter_vec!(
+                "gw_indexer_client_status",
+                "indexer client response status class count",
+                &["indexer", "status"]
+            )
+            .unwrap(),
+            errors: register_int_counter_vec!(
+                "gw_indexer_client_errors",
+                "indexer client error count",
+                &["indexer", "error"]
+            )
+            .unwrap(),
         }
     }
 }
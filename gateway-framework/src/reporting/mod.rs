@@ -1,7 +1,9 @@
 mod kafka;
+mod legacy_status;
 mod logging;
 mod metrics;
 
 pub use kafka::{EventHandlerFn, KafkaClient};
+pub use legacy_status::{legacy_error_status, legacy_status};
 pub use logging::{error_log, init, LoggingOptions, CLIENT_REQUEST_TARGET, INDEXER_REQUEST_TARGET};
 pub use metrics::{with_metric, METRICS};
@@ -1,5 +1,7 @@
 mod receipts;
 mod vouchers;
 
-pub use receipts::{ReceiptSigner, ReceiptStatus, ScalarReceipt};
+pub use receipts::{
+    ReceiptSigner, ReceiptSignerConfig, ReceiptStatus, ReceiptVersion, ScalarReceipt,
+};
 pub use vouchers::{handle_collect_receipts, handle_partial_voucher, handle_voucher};
@@ -2,7 +2,10 @@ use std::{collections::HashMap, sync::Arc, time::SystemTime};
 
 use alloy_primitives::{Address, U256};
 use alloy_sol_types::Eip712Domain;
-use ethers::signers::Wallet;
+use ethers::{
+    signers::Wallet,
+    types::{Signature, U256 as EthersU256},
+};
 use gateway_common::types::Indexing;
 use rand::RngCore;
 pub use receipts::{QueryStatus as ReceiptStatus, ReceiptPool};
@@ -18,6 +21,25 @@ pub struct ReceiptSigner {
     legacy_pools: RwLock<HashMap<Indexing, Arc<Mutex<ReceiptPool>>>>,
 }
 
+/// The [`ReceiptSigner`] configuration.
+pub struct ReceiptSignerConfig {
+    /// Secret key for TAP receipt signing.
+    pub signer: SecretKey,
+    /// The TAP verifier contract chain ID.
+    pub chain_id: U256,
+    /// The TAP verifier contract address.
+    pub verifier: Address,
+    /// Secret key for legacy receipt signing.
+    pub legacy_signer: &'static SecretKey,
+}
+
+/// The receipt formats supported by indexers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ReceiptVersion {
+    Legacy,
+    TAP,
+}
+
 pub enum ScalarReceipt {
     Legacy(u128, Vec<u8>),
     TAP(EIP712SignedMessage<Receipt>),
@@ -38,6 +60,13 @@ impl ScalarReceipt {
         }
     }
 
+    pub fn version(&self) -> ReceiptVersion {
+        match self {
+            ScalarReceipt::Legacy(_, _) => ReceiptVersion::Legacy,
+            ScalarReceipt::TAP(_) => ReceiptVersion::TAP,
+        }
+    }
+
     pub fn serialize(&self) -> String {
         match self {
             ScalarReceipt::Legacy(_, receipt) => hex::encode(&receipt[..(receipt.len() - 32)]),
@@ -68,6 +97,66 @@ impl ReceiptSigner {
         }
     }
 
+    /// Creates a new [`ReceiptSigner`] from the given configuration.
+    ///
+    /// Returns an error if the TAP receipt domain is invalid, i.e., the chain ID or the verifier
+    /// contract address is zero.
+    pub async fn from_config(config: ReceiptSignerConfig) -> anyhow::Result<Self> {
+        if config.chain_id.is_zero() {
+            anyhow::bail!("invalid TAP receipt domain: zero chain ID");
+        }
+        if config.verifier.is_zero() {
+            anyhow::bail!("invalid TAP receipt domain: zero verifier contract address");
+        }
+        Ok(Self::new(
+            config.signer,
+            config.chain_id,
+            config.verifier,
+            config.legacy_signer,
+        )
+        .await)
+    }
+
+    /// Estimate the length of a serialized receipt of the given version, without signing it. This
+    /// is an upper bound, since the lengths of some fields depend on their values.
+    pub fn estimate_receipt_bytes(&self, version: ReceiptVersion) -> usize {
+        match version {
+            ReceiptVersion::Legacy => {
+                // allocation (20) + fee (32) + receipt ID (15) + signature (65), hex encoded.
+                (20 + 32 + 15 + 65) * 2
+            }
+            ReceiptVersion::TAP => {
+                let receipt = EIP712SignedMessage {
+                    message: Receipt {
+                        allocation_id: Address::ZERO.0 .0.into(),
+                        timestamp_ns: u64::MAX,
+                        nonce: u64::MAX,
+                        value: u128::MAX,
+                    },
+                    signature: Signature {
+                        r: EthersU256::MAX,
+                        s: EthersU256::MAX,
+                        v: 28,
+                    },
+                };
+                ScalarReceipt::TAP(receipt).serialize().len()
+            }
+        }
+    }
+
+    /// Create a receipt of the given version.
+    pub async fn create_receipt_for_version(
+        &self,
+        indexing: &Indexing,
+        fee: u128,
+        version: ReceiptVersion,
+    ) -> Option<ScalarReceipt> {
+        match version {
+            ReceiptVersion::Legacy => self.create_legacy_receipt(indexing, fee).await,
+            ReceiptVersion::TAP => self.create_receipt(indexing, fee).await,
+        }
+    }
+
     pub async fn create_receipt(&self, indexing: &Indexing, fee: u128) -> Option<ScalarReceipt> {
         let allocation = *self.allocations.read().await.get(indexing)?;
         // Nonce generated with CSPRNG (ChaCha12), to avoid collisison with receipts generated by
@@ -104,6 +193,19 @@ impl ReceiptSigner {
         Some(ScalarReceipt::Legacy(fee, receipt))
     }
 
+    /// Create a receipt replacing one the indexer has already accepted, e.g. to retry a query.
+    /// The replaced receipt is recorded as failed.
+    pub async fn renew_receipt(
+        &self,
+        indexing: &Indexing,
+        receipt: &ScalarReceipt,
+    ) -> Option<ScalarReceipt> {
+        self.record_receipt(indexing, receipt, ReceiptStatus::Failure)
+            .await;
+        self.create_receipt_for_version(indexing, receipt.grt_value(), receipt.version())
+            .await
+    }
+
     pub async fn record_receipt(
         &self,
         indexing: &Indexing,
@@ -140,3 +242,82 @@ impl ReceiptSigner {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy_primitives::{Address, U256};
+    use gateway_common::types::Indexing;
+    use secp256k1::SecretKey;
+
+    use super::{ReceiptSigner, ReceiptSignerConfig, ReceiptVersion};
+
+    #[tokio::test]
+    async fn estimate_receipt_bytes() {
+        //* Given
+        let legacy_signer: &'static SecretKey =
+            Box::leak(Box::new(SecretKey::from_slice(&[0x22; 32]).unwrap()));
+        let signer = ReceiptSigner::new(
+            SecretKey::from_slice(&[0x11; 32]).unwrap(),
+            U256::from(1),
+            Address::default(),
+            legacy_signer,
+        )
+        .await;
+        let indexing = Indexing {
+            indexer: Address::default(),
+            deployment: "QmQqLJVgZLcRduoszARzRi12qGheUTWAHFf3ixMeGm2xML"
+                .parse()
+                .unwrap(),
+        };
+        signer
+            .update_allocations(HashMap::from([(indexing, Address::repeat_byte(0x01))]))
+            .await;
+        let fee = 1_000_000_000_000_000;
+
+        //* When
+        let legacy = signer.create_legacy_receipt(&indexing, fee).await.unwrap();
+        let tap = signer.create_receipt(&indexing, fee).await.unwrap();
+
+        //* Then
+        assert_eq!(
+            signer.estimate_receipt_bytes(ReceiptVersion::Legacy),
+            legacy.serialize().len()
+        );
+        let estimate = signer.estimate_receipt_bytes(ReceiptVersion::TAP);
+        let actual = tap.serialize().len();
+        assert!(estimate >= actual);
+        assert!(
+            estimate - actual <= 64,
+            "estimate: {estimate}, actual: {actual}"
+        );
+    }
+
+    #[tokio::test]
+    async fn from_config_rejects_invalid_domain() {
+        //* Given
+        let legacy_signer: &'static SecretKey =
+            Box::leak(Box::new(SecretKey::from_slice(&[0x22; 32]).unwrap()));
+        let config = |chain_id: u64, verifier: Address| ReceiptSignerConfig {
+            signer: SecretKey::from_slice(&[0x11; 32]).unwrap(),
+            chain_id: U256::from(chain_id),
+            verifier,
+            legacy_signer,
+        };
+
+        //* When
+        let zero_chain_id = ReceiptSigner::from_config(config(0, Address::repeat_byte(0x01))).await;
+        let zero_verifier = ReceiptSigner::from_config(config(1, Address::ZERO)).await;
+        let valid = ReceiptSigner::from_config(config(1, Address::repeat_byte(0x01))).await;
+
+        //* Then
+        assert!(zero_chain_id
+            .err()
+            .is_some_and(|err| err.to_string().contains("zero chain ID")));
+        assert!(zero_verifier
+            .err()
+            .is_some_and(|err| err.to_string().contains("zero verifier contract address")));
+        assert!(valid.is_ok());
+    }
+}
@@ -1,10 +1,15 @@
 pub mod subgraph;
 
-use std::str::FromStr;
+use std::{collections::HashMap, str::FromStr, time::Duration};
 
 use alloy_primitives::Address;
 use chrono::{DateTime, Utc};
+use ethers::types::Signature;
+use gateway_common::utils::timestamp;
+use itertools::Itertools as _;
+use ordered_float::NotNan;
 use serde::{de::Error, Deserialize, Deserializer};
+use serde_with::serde_as;
 
 #[derive(Clone, Debug)]
 pub struct Subscription {
@@ -12,6 +17,91 @@ pub struct Subscription {
     pub rate: u128,
 }
 
+impl Subscription {
+    /// Returns true if the given address is authorized to sign on behalf of the subscription.
+    pub fn is_authorized_signer(&self, signer: &Address) -> bool {
+        self.signers.contains(signer)
+    }
+
+    /// Verify the query auth signature, i.e., recover the signer of the (EIP-191 signed) message
+    /// and check it is authorized by the subscription.
+    pub fn verify_query_auth(&self, message: &[u8], signature: &Signature) -> bool {
+        match signature.recover(message) {
+            Ok(signer) => self.is_authorized_signer(&Address::from(signer.0)),
+            Err(_) => false,
+        }
+    }
+
+    /// Returns the highest tier whose minimum rate the subscription rate meets, or `None` if the
+    /// rate is below the lowest tier.
+    pub fn tier<'t>(&self, tiers: &'t [RateTier]) -> Option<&'t RateTier> {
+        tiers
+            .iter()
+            .filter(|tier| self.rate >= tier.min_rate)
+            .max_by_key(|tier| tier.min_rate)
+    }
+}
+
+/// A named subscription tier, e.g. `basic`, `pro` or `enterprise`.
+#[serde_as]
+#[derive(Clone, Debug, Deserialize)]
+pub struct RateTier {
+    pub name: String,
+    /// Minimum subscription rate of the tier.
+    #[serde_as(as = "serde_with::DisplayFromStr")]
+    pub min_rate: u128,
+    /// Per-query budget cap of the tier, in USD. If `None`, the tier has no budget cap.
+    #[serde_as(as = "Option<serde_with::TryFromInto<f64>>")]
+    #[serde(rename = "max_budget", default)]
+    pub max_budget_usd: Option<NotNan<f64>>,
+}
+
+/// Default maximum age of the cached subscriptions before they are no longer served.
+pub const DEFAULT_MAX_STALENESS: Duration = Duration::from_secs(10 * 60);
+
+/// Default maximum number of authorized signers kept per user, including the user itself.
+pub const DEFAULT_MAX_SIGNERS: usize = 100;
+
+/// The last-known active subscriptions by user, and the time they were last updated.
+///
+/// If fetching the subscriptions fails, the last-known subscriptions are kept and served until
+/// they are older than the maximum staleness.
+#[derive(Clone, Debug, Default)]
+pub struct SubscriptionsCache {
+    pub subscriptions: HashMap<Address, Subscription>,
+    /// Unix timestamp, in milliseconds, of the last update. Subscriptions that were never fetched
+    /// have no update time.
+    pub updated_at_ms: Option<u64>,
+}
+
+impl SubscriptionsCache {
+    /// Returns true if the subscriptions were last updated more than `max_staleness` before
+    /// `now_ms`.
+    pub fn is_stale(&self, now_ms: u64, max_staleness: Duration) -> bool {
+        timestamp::is_stale(self.updated_at_ms, now_ms, max_staleness)
+    }
+}
+
+/// Builds the index of authorized signers to the user that owns the subscription, from the
+/// subscriptions by user.
+///
+/// A signer authorized by multiple users is attributed to the user with the lowest address, except
+/// for a user's own address, which is always attributed to that user.
+pub fn build_signer_index(
+    subscriptions: &HashMap<Address, Subscription>,
+) -> HashMap<Address, Address> {
+    let mut index: HashMap<Address, Address> = HashMap::new();
+    for (user, subscription) in subscriptions {
+        for signer in &subscription.signers {
+            let owner = index.entry(*signer).or_insert(*user);
+            if (*owner != *signer) && ((*user == *signer) || (*user < *owner)) {
+                *owner = *user;
+            }
+        }
+    }
+    index
+}
+
 #[derive(Clone, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct User {
@@ -20,6 +110,17 @@ pub struct User {
     pub authorized_signers: Vec<AuthorizedSigner>,
 }
 
+impl User {
+    /// Returns the deduplicated set of signers authorized to act on behalf of the user, including
+    /// the user itself.
+    pub fn all_signers(&self) -> Vec<Address> {
+        std::iter::once(self.id)
+            .chain(self.authorized_signers.iter().map(|signer| signer.signer))
+            .unique()
+            .collect()
+    }
+}
+
 #[derive(Clone, Debug, Deserialize)]
 pub struct AuthorizedSigner {
     pub signer: Address,
@@ -36,6 +137,55 @@ pub struct ActiveSubscription {
     pub rate: u128,
 }
 
+impl ActiveSubscription {
+    /// Returns the budget accrued by the subscription at the given time, i.e., the subscription
+    /// rate (per second) times the seconds elapsed since the subscription start.
+    ///
+    /// The budget stops accruing at the subscription end.
+    pub fn accrued(&self, now: DateTime<Utc>) -> u128 {
+        let elapsed_secs = (now.min(self.end) - self.start).num_seconds().max(0) as u128;
+        self.rate.saturating_mul(elapsed_secs)
+    }
+}
+
+/// Tracks the cumulative spend of the subscription users against their subscriptions accrued
+/// budget.
+#[derive(Debug, Default)]
+pub struct SubscriptionLedger {
+    subscriptions: HashMap<Address, ActiveSubscription>,
+    spent: HashMap<Address, u128>,
+}
+
+impl SubscriptionLedger {
+    /// Creates a new [`SubscriptionLedger`] for the given users' subscriptions.
+    pub fn new(subscriptions: HashMap<Address, ActiveSubscription>) -> Self {
+        Self {
+            subscriptions,
+            spent: HashMap::new(),
+        }
+    }
+
+    /// Records the given amount as spent by the user.
+    pub fn charge(&mut self, user: Address, amount: u128) {
+        let spent = self.spent.entry(user).or_default();
+        *spent = spent.saturating_add(amount);
+    }
+
+    /// Returns the total amount spent by the user.
+    pub fn spent(&self, user: &Address) -> u128 {
+        self.spent.get(user).copied().unwrap_or(0)
+    }
+
+    /// Returns the user's budget remaining at the given time. Users without a subscription have no
+    /// budget.
+    pub fn remaining(&self, user: &Address, now: DateTime<Utc>) -> u128 {
+        let Some(subscription) = self.subscriptions.get(user) else {
+            return 0;
+        };
+        subscription.accrued(now).saturating_sub(self.spent(user))
+    }
+}
+
 fn deserialize_datetime_utc<'de, D>(deserializer: D) -> Result<DateTime<Utc>, D::Error>
 where
     D: Deserializer<'de>,
@@ -57,6 +207,7 @@ where
 #[cfg(test)]
 mod tests {
     use anyhow::ensure;
+    use ethers::signers::{LocalWallet, Signer as _};
     use serde_json::json;
 
     use super::*;
@@ -82,4 +233,165 @@ mod tests {
         ensure!(result.is_ok(), "failed to parse example: {:?}", result);
         Ok(())
     }
+
+    #[tokio::test]
+    async fn verify_query_auth_signature() {
+        //* Given
+        let authorized = LocalWallet::from_bytes(&[0x11; 32]).unwrap();
+        let unauthorized = LocalWallet::from_bytes(&[0x22; 32]).unwrap();
+        let subscription = Subscription {
+            signers: vec![Address::from(authorized.address().0)],
+            rate: 1,
+        };
+        let message = b"{ tokens { id } }";
+
+        //* When
+        let authorized_signature = authorized.sign_message(message).await.unwrap();
+        let unauthorized_signature = unauthorized.sign_message(message).await.unwrap();
+
+        //* Then
+        assert!(subscription.verify_query_auth(message, &authorized_signature));
+        assert!(!subscription.verify_query_auth(message, &unauthorized_signature));
+        // The signature is not valid for a different message
+        assert!(!subscription.verify_query_auth(b"{ pairs { id } }", &authorized_signature));
+    }
+
+    #[test]
+    fn user_all_signers_are_deduplicated() {
+        //* Given
+        let user_id = Address::repeat_byte(0x01);
+        let signer = Address::repeat_byte(0x02);
+        let user = User {
+            id: user_id,
+            authorized_signers: [signer, user_id, signer]
+                .into_iter()
+                .map(|signer| AuthorizedSigner { signer })
+                .collect(),
+        };
+
+        //* When
+        let signers = user.all_signers();
+
+        //* Then
+        assert_eq!(signers, vec![user_id, signer]);
+    }
+
+    #[test]
+    fn signer_index_tiebreak() {
+        //* Given
+        let user_a = Address::repeat_byte(0x01);
+        let user_b = Address::repeat_byte(0x02);
+        let user_c = Address::repeat_byte(0x03);
+        let shared = Address::repeat_byte(0x10);
+        let exclusive = Address::repeat_byte(0x11);
+        let subscription = |signers: Vec<Address>| Subscription { signers, rate: 1 };
+        let subscriptions = HashMap::from([
+            (user_a, subscription(vec![user_a])),
+            (user_b, subscription(vec![user_b, shared, user_c])),
+            (user_c, subscription(vec![user_c, shared, exclusive])),
+        ]);
+
+        //* When
+        let index = build_signer_index(&subscriptions);
+
+        //* Then
+        assert_eq!(index.len(), 5);
+        assert_eq!(index[&user_a], user_a);
+        assert_eq!(index[&user_b], user_b);
+        // The user's own address is attributed to the user, even if authorized by a lower address
+        assert_eq!(index[&user_c], user_c);
+        // A signer authorized by multiple users is attributed to the lowest address
+        assert_eq!(index[&shared], user_b);
+        assert_eq!(index[&exclusive], user_c);
+    }
+
+    #[test]
+    fn subscription_rate_tiers() {
+        //* Given
+        let tier = |name: &str, min_rate: u128, max_budget_usd: f64| RateTier {
+            name: name.to_string(),
+            min_rate,
+            max_budget_usd: Some(NotNan::new(max_budget_usd).unwrap()),
+        };
+        // Tiers are not required to be sorted
+        let tiers = [
+            tier("pro", 1_000, 0.001),
+            tier("basic", 100, 0.0001),
+            tier("enterprise", 10_000, 0.01),
+        ];
+        let tier_name = |rate: u128| {
+            Subscription {
+                signers: vec![],
+                rate,
+            }
+            .tier(&tiers)
+            .map(|tier| tier.name.as_str())
+        };
+
+        //* Then
+        assert_eq!(tier_name(99), None);
+        assert_eq!(tier_name(100), Some("basic"));
+        assert_eq!(tier_name(999), Some("basic"));
+        assert_eq!(tier_name(1_000), Some("pro"));
+        assert_eq!(tier_name(u128::MAX), Some("enterprise"));
+        assert_eq!(
+            Subscription {
+                signers: vec![],
+                rate: 1_000,
+            }
+            .tier(&[]),
+            None
+        );
+    }
+
+    #[test]
+    fn ledger_remaining_budget() {
+        //* Given
+        let user = Address::repeat_byte(0x01);
+        let start = DateTime::<Utc>::from_timestamp(1_676_507_163, 0).unwrap();
+        let subscription = ActiveSubscription {
+            user: User {
+                id: user,
+                authorized_signers: vec![],
+            },
+            start,
+            end: start + chrono::Duration::seconds(60),
+            rate: 10,
+        };
+        let mut ledger = SubscriptionLedger::new(HashMap::from([(user, subscription)]));
+        let at = |secs| start + chrono::Duration::seconds(secs);
+
+        //* When
+        ledger.charge(user, 50);
+        let remaining_at_10s = ledger.remaining(&user, at(10));
+        ledger.charge(user, 100);
+        let remaining_at_30s = ledger.remaining(&user, at(30));
+        ledger.charge(user, 500);
+        let remaining_at_60s = ledger.remaining(&user, at(60));
+        let remaining_after_end = ledger.remaining(&user, at(120));
+
+        //* Then
+        assert_eq!(remaining_at_10s, 50);
+        assert_eq!(remaining_at_30s, 150);
+        // The spend exceeds the accrued budget, so the remaining budget floors at zero
+        assert_eq!(ledger.spent(&user), 650);
+        assert_eq!(remaining_at_60s, 0);
+        assert_eq!(remaining_after_end, 0);
+        assert_eq!(ledger.remaining(&Address::repeat_byte(0x02), at(30)), 0);
+    }
+
+    #[test]
+    fn subscriptions_cache_staleness() {
+        //* Given
+        let max_staleness = Duration::from_secs(600);
+        let cache = SubscriptionsCache {
+            subscriptions: HashMap::new(),
+            updated_at_ms: Some(1_000_000),
+        };
+
+        //* Then
+        assert!(!cache.is_stale(1_000_000 + 600_000, max_staleness));
+        assert!(cache.is_stale(1_000_000 + 600_001, max_staleness));
+        assert!(!SubscriptionsCache::default().is_stale(u64::MAX, max_staleness));
+    }
 }
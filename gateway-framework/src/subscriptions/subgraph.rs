@@ -1,4 +1,4 @@
-use std::{collections::HashMap, time::Duration};
+use std::time::Duration;
 
 use alloy_primitives::Address;
 use anyhow::anyhow;
@@ -8,23 +8,27 @@ use tokio::{
     sync::watch,
     time::{interval, MissedTickBehavior},
 };
+use url::Url;
 
-use crate::subscriptions::{ActiveSubscription, Subscription};
+use crate::subscriptions::{ActiveSubscription, Subscription, SubscriptionsCache, User};
 
 pub struct Client {
     subgraph_client: subgraph_client::Client,
-    subscriptions: watch::Sender<HashMap<Address, Subscription>>,
+    subscriptions: watch::Sender<SubscriptionsCache>,
+    max_signers: usize,
 }
 
 impl Client {
     pub async fn create(
         subgraph_client: subgraph_client::Client,
         allow_empty: bool,
-    ) -> watch::Receiver<HashMap<Address, Subscription>> {
+        max_signers: usize,
+    ) -> watch::Receiver<SubscriptionsCache> {
         let (tx, mut rx) = watch::channel(Default::default());
         let mut client = Client {
             subgraph_client,
             subscriptions: tx,
+            max_signers,
         };
 
         tokio::spawn(async move {
@@ -33,6 +37,7 @@ impl Client {
             loop {
                 interval.tick().await;
 
+                // On failure, the last-known subscriptions are kept until they become stale
                 if let Err(poll_active_subscriptions_err) = client.poll_active_subscriptions().await
                 {
                     tracing::error!(%poll_active_subscriptions_err);
@@ -41,7 +46,7 @@ impl Client {
         });
 
         if !allow_empty {
-            rx.wait_for(|subscriptions| !subscriptions.is_empty())
+            rx.wait_for(|cache| !cache.subscriptions.is_empty())
                 .await
                 .unwrap();
         }
@@ -49,8 +54,7 @@ impl Client {
     }
 
     async fn poll_active_subscriptions(&mut self) -> anyhow::Result<()> {
-        // Serve queries for subscriptions that end 10 minutes ago and later.
-        let active_sub_end = (unix_timestamp() / 1000) - (60 * 10);
+        let active_sub_end = active_subscriptions_min_end();
 
         let query = format!(
             r#"
@@ -84,10 +88,13 @@ impl Client {
             .map_err(|err| anyhow!(err))?;
         if active_subscriptions_response.is_empty() {
             tracing::warn!("discarding empty update (active_subscriptions)");
+            // The fetch succeeded, so the last-known subscriptions are still up to date
+            self.subscriptions
+                .send_modify(|cache| cache.updated_at_ms = Some(unix_timestamp()));
             return Ok(());
         }
 
-        let subscriptions_map = active_subscriptions_response
+        let subscriptions = active_subscriptions_response
             .into_iter()
             .filter_map(|ActiveSubscription { user, rate, .. }| {
                 // Skip subscriptions with a rate of 0
@@ -96,18 +103,224 @@ impl Client {
                     return None;
                 }
 
-                let signers = user
-                    .authorized_signers
-                    .into_iter()
-                    .map(|signer| signer.signer)
-                    .chain([user.id])
-                    .collect();
+                let signers = limit_signers(&user, self.max_signers);
                 Some((user.id, Subscription { signers, rate }))
             })
             .collect();
 
-        self.subscriptions.send(subscriptions_map)?;
+        self.subscriptions.send(SubscriptionsCache {
+            subscriptions,
+            updated_at_ms: Some(unix_timestamp()),
+        })?;
 
         Ok(())
     }
 }
+
+/// Returns the user's authorized signers, truncated to `max_signers`. The user itself is always
+/// kept, as it is the first of the signers.
+fn limit_signers(user: &User, max_signers: usize) -> Vec<Address> {
+    let mut signers = user.all_signers();
+    if signers.len() > max_signers {
+        tracing::warn!(
+            user = ?user.id,
+            signers = signers.len(),
+            max_signers,
+            "truncating authorized signers"
+        );
+        signers.truncate(max_signers.max(1));
+    }
+    signers
+}
+
+/// Fetch the user's active subscriptions from the subscriptions subgraph.
+pub async fn fetch_active_subscriptions(
+    client: reqwest::Client,
+    url: Url,
+    user: Address,
+) -> anyhow::Result<Vec<ActiveSubscription>> {
+    let mut subgraph_client = subgraph_client::Client::new(client, url);
+    let active_sub_end = active_subscriptions_min_end();
+
+    let query = format!(
+        r#"
+        userSubscriptions(
+            block: $block
+            orderBy: id, orderDirection: asc
+            first: $first
+            where: {{
+                id_gt: $last
+                user: "{user:?}"
+                end_gte: {active_sub_end}
+                cancelled: false
+            }}
+        ) {{
+            id
+            user {{
+                id
+                authorizedSigners {{
+                    signer
+                }}
+            }}
+            start
+            end
+            rate
+        }}
+        "#,
+    );
+    subgraph_client
+        .paginated_query::<ActiveSubscription>(query, 200)
+        .await
+        .map_err(|err| anyhow!(err))
+}
+
+/// The minimum end timestamp, in seconds, of the subscriptions considered active.
+///
+/// Serve queries for subscriptions that end 10 minutes ago and later.
+fn active_subscriptions_min_end() -> u64 {
+    (unix_timestamp() / 1000) - (60 * 10)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy_primitives::Address;
+    use axum::{routing::post, Json, Router};
+    use serde_json::{json, Value};
+    use thegraph_core::client as subgraph_client;
+    use tokio::sync::watch;
+
+    use super::{fetch_active_subscriptions, limit_signers, Client};
+    use crate::{
+        subscriptions::{AuthorizedSigner, Subscription, SubscriptionsCache, User},
+        test_utils::serve,
+    };
+
+    #[tokio::test]
+    async fn fetch_user_active_subscriptions() {
+        //* Given
+        let user = Address::repeat_byte(0x01);
+        let subscription = move |id: &str, start: &str| {
+            json!({
+                "id": id,
+                "user": {
+                    "id": format!("{user:?}"),
+                    "authorizedSigners": [{ "signer": format!("{:?}", Address::repeat_byte(0x02)) }],
+                },
+                "start": start,
+                "end": "4102444800",
+                "rate": "100000000000000",
+            })
+        };
+        let page = move |results: Vec<Value>| {
+            json!({
+                "data": {
+                    "meta": { "block": { "number": 1, "hash": format!("0x{}", "aa".repeat(32)), "timestamp": 1 } },
+                    "results": results,
+                }
+            })
+        };
+        let url = serve(Router::new().route(
+            "/",
+            post(move |Json(request): Json<Value>| async move {
+                // Return both subscriptions in the first page, and an empty page afterwards
+                let results = match request["variables"]["last"].as_str() {
+                    Some("") | None => vec![
+                        subscription("0x01", "1676507163"),
+                        subscription("0x02", "1676507701"),
+                    ],
+                    Some(_) => vec![],
+                };
+                Json(page(results))
+            }),
+        ))
+        .await;
+
+        //* When
+        let subscriptions = fetch_active_subscriptions(reqwest::Client::new(), url, user).await;
+
+        //* Then
+        let subscriptions = subscriptions.expect("failed to fetch subscriptions");
+        assert_eq!(subscriptions.len(), 2);
+        assert!(subscriptions.iter().all(|sub| sub.user.id == user));
+        assert_eq!(subscriptions[0].start.timestamp(), 1676507163);
+        assert_eq!(subscriptions[1].start.timestamp(), 1676507701);
+        assert_eq!(subscriptions[0].rate, 100000000000000);
+    }
+
+    #[tokio::test]
+    async fn empty_update_refreshes_the_update_time() {
+        //* Given
+        let url = serve(Router::new().route(
+            "/",
+            post(|| async {
+                Json(json!({
+                    "data": {
+                        "meta": { "block": { "number": 1, "hash": format!("0x{}", "aa".repeat(32)), "timestamp": 1 } },
+                        "results": [],
+                    }
+                }))
+            }),
+        ))
+        .await;
+        let user = Address::repeat_byte(0x01);
+        let (tx, rx) = watch::channel(SubscriptionsCache {
+            subscriptions: HashMap::from([(
+                user,
+                Subscription {
+                    signers: vec![user],
+                    rate: 1,
+                },
+            )]),
+            updated_at_ms: Some(1),
+        });
+        let mut client = Client {
+            subgraph_client: subgraph_client::Client::new(reqwest::Client::new(), url),
+            subscriptions: tx,
+            max_signers: 10,
+        };
+
+        //* When
+        let result = client.poll_active_subscriptions().await;
+
+        //* Then
+        assert!(result.is_ok());
+        let cache = rx.borrow();
+        // The last-known subscriptions are kept, and their update time is refreshed
+        assert!(cache.subscriptions.contains_key(&user));
+        assert!(cache
+            .updated_at_ms
+            .is_some_and(|updated_at_ms| updated_at_ms > 1));
+    }
+
+    #[test]
+    fn signers_above_limit_are_truncated() {
+        //* Given
+        let user = User {
+            id: Address::repeat_byte(0x01),
+            authorized_signers: (0x02..=0x06)
+                .map(|byte| AuthorizedSigner {
+                    signer: Address::repeat_byte(byte),
+                })
+                .collect(),
+        };
+
+        //* When
+        let limited = limit_signers(&user, 3);
+        let unlimited = limit_signers(&user, 10);
+
+        //* Then
+        assert_eq!(
+            limited,
+            vec![
+                Address::repeat_byte(0x01),
+                Address::repeat_byte(0x02),
+                Address::repeat_byte(0x03),
+            ]
+        );
+        assert_eq!(unlimited.len(), 6);
+        // The user itself is kept even if the limit is zero
+        assert_eq!(limit_signers(&user, 0), vec![Address::repeat_byte(0x01)]);
+    }
+}
@@ -0,0 +1,13 @@
+//! Utilities shared by the gateway crates' tests.
+
+use axum::Router;
+use tokio::net::TcpListener;
+use url::Url;
+
+/// Serve the given router on a random local port, returning its base URL.
+pub async fn serve(router: Router) -> Url {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, router).await.unwrap() });
+    format!("http://{addr}/").parse().unwrap()
+}
@@ -82,6 +82,20 @@ pub fn resolve_block_requirements(
     })
 }
 
+/// Parse the block constraint of the query, e.g. `block: { number: N }` or `block: { hash: H }`.
+///
+/// Returns `None` if the query is invalid, has no block constraints, or its fields have different
+/// block constraints.
+pub fn parse_block_constraint(query: &str) -> Option<BlockConstraint> {
+    let context = Context::new(query, "").ok()?;
+    block_constraints(&context)
+        .ok()?
+        .into_iter()
+        .filter(|constraint| *constraint != BlockConstraint::Unconstrained)
+        .exactly_one()
+        .ok()
+}
+
 fn block_constraints(context: &Context) -> Result<BTreeSet<BlockConstraint>, Error> {
     let mut constraints = BTreeSet::new();
     let vars = &context.variables;
@@ -466,6 +480,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_query_block_constraint() {
+        let hash: BlockHash = bytes_from_id(54321).into();
+        let hash_query = format!("{{ a(block:{{hash:{:?}}}) b }}", hash.to_string());
+
+        assert_eq!(
+            parse_block_constraint("{ a(block:{number:10}) b }"),
+            Some(BlockConstraint::Number(10))
+        );
+        assert_eq!(
+            parse_block_constraint(&hash_query),
+            Some(BlockConstraint::Hash(hash))
+        );
+        assert_eq!(parse_block_constraint("{ a b }"), None);
+        assert_eq!(
+            parse_block_constraint("{ a(block:{number:1}) b(block:{number:2}) }"),
+            None
+        );
+    }
+
     #[test]
     fn query_contains_introspection() {
         let query = "{ __schema { queryType { name } } }";
@@ -22,15 +22,17 @@ use gateway_common::{
 use gateway_framework::{
     auth::AuthToken,
     blocks::Block,
-    budgets::USD,
+    budgets::{round_fee, USD},
     chains::ChainReader,
     errors::{Error, IndexerError, UnavailableReason},
     network::{
         discovery::Status,
         indexing_performance::{IndexingPerformance, Snapshot},
     },
-    reporting::{with_metric, KafkaClient, CLIENT_REQUEST_TARGET, INDEXER_REQUEST_TARGET, METRICS},
-    scalar::{ReceiptStatus, ScalarReceipt},
+    reporting::{
+        self, with_metric, KafkaClient, CLIENT_REQUEST_TARGET, INDEXER_REQUEST_TARGET, METRICS,
+    },
+    scalar::{ReceiptSigner, ReceiptStatus, ReceiptVersion, ScalarReceipt},
     topology::network::{Deployment, GraphNetwork, Subgraph},
 };
 use headers::ContentType;
@@ -41,7 +43,10 @@ use prost::bytes::Buf;
 use rand::{rngs::SmallRng, Rng as _, SeedableRng as _};
 use serde::{Deserialize, Serialize};
 use serde_json::value::RawValue;
-use thegraph_core::types::{attestation, DeploymentId};
+use thegraph_core::types::{
+    attestation::{self, Attestation},
+    DeploymentId,
+};
 use thegraph_graphql_http::http::response::{Error as GQLError, ResponseBody as GQLResponseBody};
 use tokio::sync::mpsc;
 use tracing::Instrument;
@@ -67,6 +72,9 @@ mod query_settings;
 
 const SELECTION_LIMIT: usize = 3;
 
+/// The header set by Cloudflare to the client's country code.
+const CLIENT_REGION_HEADER: &str = "cf-ipcountry";
+
 #[derive(Debug, Deserialize)]
 pub struct QueryBody {
     pub query: String,
@@ -78,6 +86,7 @@ pub struct Selection {
     pub url: Url,
     pub receipt: ScalarReceipt,
     pub blocks_behind: u64,
+    pub seconds_behind: u32,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -92,6 +101,7 @@ pub async fn handle_query(
 ) -> Result<Response<String>, Error> {
     let start_time = Instant::now();
     let timestamp = unix_timestamp();
+    let client_region = client_region(&headers);
 
     // Check if the query selector is authorized by the auth token and
     // resolve the subgraph deployments for the query.
@@ -102,13 +112,14 @@ pub async fn handle_query(
                 return Err(Error::Auth(anyhow!("Subgraph not authorized by user")));
             }
 
-            resolve_subgraph_deployments(&ctx.network, &selector)?
+            resolve_subgraph_deployments(&ctx.network, ctx.blocked_deployments, &selector)?
         }
         QuerySelector::Deployment(_) => {
             // Authorization is based on the "authorized subgraphs" allowlist. We need to resolve
             // the subgraph deployments to check if any of the deployment's subgraphs are
             // authorized, otherwise return an error.
-            let (deployments, subgraph) = resolve_subgraph_deployments(&ctx.network, &selector)?;
+            let (deployments, subgraph) =
+                resolve_subgraph_deployments(&ctx.network, ctx.blocked_deployments, &selector)?;
 
             // If none of the deployment's subgraphs are authorized, return an error.
             let deployment_subgraphs = deployments
@@ -168,11 +179,12 @@ pub async fn handle_query(
             Ok(_) => "200 OK".to_string(),
             Err(err) => err.to_string(),
         };
-        let (legacy_status_message, legacy_status_code) = reports::legacy_status(&result);
+        let (legacy_status_message, legacy_status_code) = reporting::legacy_status(&result);
         tracing::info!(
             target: CLIENT_REQUEST_TARGET,
             start_time_ms = timestamp,
             deployment,
+            client_region,
             %status_message,
             %legacy_status_message,
             legacy_status_code,
@@ -189,10 +201,24 @@ pub async fn handle_query(
     })
 }
 
+/// Returns the client's region, if known, from the request headers.
+///
+/// Cloudflare reports unknown regions as `XX`.
+fn client_region(headers: &HeaderMap) -> Option<String> {
+    let region = headers.get(CLIENT_REGION_HEADER)?.to_str().ok()?.trim();
+    if region.is_empty() || region.eq_ignore_ascii_case("XX") {
+        return None;
+    }
+    Some(region.to_ascii_uppercase())
+}
+
 /// Given a query selector, resolve the subgraph deployments for the query. If the selector is a subgraph ID, return
 /// the subgraph's deployment instances. If the selector is a deployment ID, return the deployment instance.
+///
+/// Blocked deployments are never resolved.
 fn resolve_subgraph_deployments(
     network: &GraphNetwork,
+    blocked_deployments: &HashSet<DeploymentId>,
     selector: &QuerySelector,
 ) -> Result<(Vec<Arc<Deployment>>, Option<Subgraph>), Error> {
     match selector {
@@ -202,10 +228,12 @@ fn resolve_subgraph_deployments(
                 .subgraph_by_id(subgraph_id)
                 .ok_or_else(|| Error::SubgraphNotFound(anyhow!("{subgraph_id}")))?;
 
-            // Get the subgraph's chain (from the last of its deployments)
+            // Get the subgraph's chain (from the last of its unblocked deployments)
             let subgraph_chain = subgraph
                 .deployments
-                .last()
+                .iter()
+                .rev()
+                .find(|deployment| !blocked_deployments.contains(&deployment.id))
                 .map(|deployment| deployment.manifest.network.clone())
                 .ok_or_else(|| Error::SubgraphNotFound(anyhow!("no matching deployments")))?;
 
@@ -214,6 +242,7 @@ fn resolve_subgraph_deployments(
             let versions = subgraph
                 .deployments
                 .iter()
+                .filter(|deployment| !blocked_deployments.contains(&deployment.id))
                 .filter(|deployment| deployment.manifest.network == subgraph_chain)
                 .cloned()
                 .collect();
@@ -222,9 +251,12 @@ fn resolve_subgraph_deployments(
         }
         QuerySelector::Deployment(deployment_id) => {
             // Get the deployment by ID
-            let deployment = network.deployment_by_id(deployment_id).ok_or_else(|| {
-                Error::SubgraphNotFound(anyhow!("deployment not found: {deployment_id}"))
-            })?;
+            let deployment = network
+                .deployment_by_id(deployment_id)
+                .filter(|deployment| !blocked_deployments.contains(&deployment.id))
+                .ok_or_else(|| {
+                    Error::SubgraphNotFound(anyhow!("deployment not found: {deployment_id}"))
+                })?;
 
             Ok((vec![deployment], None))
         }
@@ -248,6 +280,7 @@ async fn handle_client_query_inner(
 
     let payload: QueryBody =
         serde_json::from_reader(payload.reader()).map_err(|err| Error::BadQuery(err.into()))?;
+    check_query_size(&payload.query, ctx.max_query_bytes)?;
 
     let mut indexer_errors: BTreeMap<Address, IndexerError> = Default::default();
 
@@ -294,7 +327,8 @@ async fn handle_client_query_inner(
         %variables,
     );
 
-    let grt_per_usd = *ctx.grt_per_usd.borrow();
+    let exchange_rate = *ctx.grt_per_usd.borrow();
+    let grt_per_usd = exchange_rate.rate;
     let one_grt = NotNan::new(1e18).unwrap();
     let mut budget = *(ctx.budgeter.query_fees_target.0 * grt_per_usd * one_grt) as u128;
     let query_settings = query_settings.unwrap_or_default();
@@ -336,24 +370,10 @@ async fn handle_client_query_inner(
     });
     tracing::debug!(chain_head, blocks_per_minute, ?block_requirements);
 
-    // List holding the indexers that support Scalar TAP.
-    //
-    // This is a temporary solution determine which indexers support Scalar TAP. This will be
-    // removed once the network service is integrated.
-    let mut candidates_with_scalar_tap_support = HashSet::new();
-
     let mut candidates = Vec::new();
     {
         let perf = ctx.indexing_perf.latest();
         for indexing in available_indexers {
-            if let Some(status) = indexing_statuses.get(&indexing) {
-                // If the indexer status indicates it supports Scalar TAP, add it to the set of
-                // indexers with Scalar TAP support.
-                if !status.legacy_scalar {
-                    candidates_with_scalar_tap_support.insert(indexing.indexer);
-                }
-            }
-
             match prepare_candidate(
                 &ctx.network,
                 &indexing_statuses,
@@ -400,15 +420,19 @@ async fn handle_client_query_inner(
         let min_fee = *ctx.budgeter.min_indexer_fees.borrow();
         let min_fee = *(min_fee.0 * grt_per_usd * one_grt) / selections_len as f64;
         let indexer_fee = candidate.fee.as_f64() * budget as f64;
-        let fee = indexer_fee.max(min_fee) as u128;
+        let fee = round_fee(
+            NotNan::new(indexer_fee.max(min_fee)).unwrap_or_default(),
+            ctx.fee_rounding,
+        );
 
-        let receipt = match if candidates_with_scalar_tap_support.contains(&indexing.indexer) {
-            ctx.receipt_signer.create_receipt(&indexing, fee).await
-        } else {
-            ctx.receipt_signer
-                .create_legacy_receipt(&indexing, fee)
-                .await
-        } {
+        let receipt_version = indexing_statuses
+            .get(&indexing)
+            .map_or(ReceiptVersion::Legacy, Status::receipt_version);
+        let receipt = match ctx
+            .receipt_signer
+            .create_receipt_for_version(&indexing, fee, receipt_version)
+            .await
+        {
             Some(receipt) => receipt,
             None => {
                 tracing::error!(?indexing, "failed to create receipt");
@@ -423,6 +447,7 @@ async fn handle_client_query_inner(
             url: candidate.url.clone(),
             receipt,
             blocks_behind: blocks_behind as u64,
+            seconds_behind: candidate.seconds_behind,
         });
     }
     if selections.is_empty() {
@@ -453,7 +478,7 @@ async fn handle_client_query_inner(
 
     let mut total_indexer_fees_grt: u128 = 0;
     let (outcome_tx, mut outcome_rx) = mpsc::channel(SELECTION_LIMIT);
-    for (selection, indexer_request) in selections.into_iter().zip(indexer_requests) {
+    for (mut selection, indexer_request) in selections.into_iter().zip(indexer_requests) {
         let deployment = deployments
             .iter()
             .find(|deployment| deployment.id == selection.indexing.deployment)
@@ -461,17 +486,24 @@ async fn handle_client_query_inner(
             .clone();
         let indexer_query_context = IndexerQueryContext {
             indexer_client: ctx.indexer_client.clone(),
+            receipt_signer: ctx.receipt_signer,
+            retries: ctx.indexer_query_retries,
             kafka_client: ctx.kafka_client,
             chain: chain.clone(),
             attestation_domain: ctx.attestation_domain,
             indexing_perf: ctx.indexing_perf.clone(),
+            block_time: ctx
+                .chain_block_times
+                .get(&deployment.manifest.network)
+                .copied(),
             deployment,
             response_time: Duration::default(),
+            renewed_fees_grt: 0,
         };
 
         total_indexer_fees_grt += selection.receipt.grt_value();
 
-        let indexer_query_context = indexer_query_context.clone();
+        let mut indexer_query_context = indexer_query_context.clone();
         let outcome_tx = outcome_tx.clone();
         // We must manually construct this span before the spawned task, since otherwise
         // there's a race between creating this span and another indexer responding which will
@@ -484,8 +516,12 @@ async fn handle_client_query_inner(
         let receipt_signer = ctx.receipt_signer;
         tokio::spawn(
             async move {
-                let response =
-                    handle_indexer_query(indexer_query_context, &selection, indexer_request).await;
+                let response = handle_indexer_query(
+                    &mut indexer_query_context,
+                    &mut selection,
+                    indexer_request,
+                )
+                .await;
                 let receipt_status = match &response {
                     Ok(_) => ReceiptStatus::Success,
                     Err(IndexerError::Timeout) => ReceiptStatus::Unknown,
@@ -495,7 +531,10 @@ async fn handle_client_query_inner(
                     .record_receipt(&selection.indexing, &selection.receipt, receipt_status)
                     .await;
 
-                let _ = outcome_tx.send((selection, response)).await;
+                let renewed_fees_grt = indexer_query_context.renewed_fees_grt;
+                let _ = outcome_tx
+                    .send((selection, renewed_fees_grt, response))
+                    .await;
             }
             .instrument(span),
         );
@@ -503,29 +542,39 @@ async fn handle_client_query_inner(
     // This must be dropped to ensure the `outcome_rx.recv()` loop below can eventyually stop.
     drop(outcome_tx);
 
+    // Fees of receipts renewed on retries are only known once the indexer queries complete.
+    let mut success = None;
+    while let Some((selection, renewed_fees_grt, result)) = outcome_rx.recv().await {
+        total_indexer_fees_grt += renewed_fees_grt;
+        match result {
+            Err(err) => {
+                indexer_errors.insert(selection.indexing.indexer, err);
+            }
+            Ok(outcome) => {
+                success = Some((selection, outcome));
+                break;
+            }
+        };
+    }
+
     let total_indexer_fees_usd =
         USD(NotNan::new(total_indexer_fees_grt as f64 * 1e-18).unwrap() / grt_per_usd);
     tracing::info!(
         target: CLIENT_REQUEST_TARGET,
         indexer_fees_grt = (total_indexer_fees_grt as f64 * 1e-18) as f32,
         indexer_fees_usd = *total_indexer_fees_usd.0 as f32,
+        grt_per_usd_stale = exchange_rate.is_stale(unix_timestamp(), ctx.grt_per_usd_max_age),
     );
 
-    while let Some((selection, result)) = outcome_rx.recv().await {
-        match result {
-            Err(err) => {
-                indexer_errors.insert(selection.indexing.indexer, err);
-            }
-            Ok(outcome) => {
-                let _ = ctx.budgeter.feedback.send(total_indexer_fees_usd);
+    match success {
+        Some((selection, outcome)) => {
+            let _ = ctx.budgeter.feedback.send(total_indexer_fees_usd);
 
-                tracing::debug!(?indexer_errors);
-                return Ok((selection, outcome));
-            }
-        };
+            tracing::debug!(?indexer_errors);
+            Ok((selection, outcome))
+        }
+        None => Err(Error::BadIndexers(indexer_errors)),
     }
-
-    Err(Error::BadIndexers(indexer_errors))
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -610,23 +659,30 @@ fn perf(
 #[derive(Clone)]
 struct IndexerQueryContext {
     pub indexer_client: IndexerClient,
+    pub receipt_signer: &'static ReceiptSigner,
+    /// Number of times a failed query is retried on the same indexer.
+    pub retries: usize,
     pub kafka_client: &'static KafkaClient,
     pub chain: ChainReader,
     pub attestation_domain: &'static Eip712Domain,
     pub indexing_perf: IndexingPerformance,
     pub deployment: Arc<Deployment>,
+    /// The expected block time of the deployment's chain, if configured.
+    pub block_time: Option<Duration>,
     pub response_time: Duration,
+    /// Fees of the receipts renewed on retries, in GRT wei.
+    pub renewed_fees_grt: u128,
 }
 
 async fn handle_indexer_query(
-    mut ctx: IndexerQueryContext,
-    selection: &Selection,
+    ctx: &mut IndexerQueryContext,
+    selection: &mut Selection,
     indexer_request: String,
 ) -> Result<ResponsePayload, IndexerError> {
     let indexing = selection.indexing;
     let deployment = indexing.deployment.to_string();
 
-    let result = handle_indexer_query_inner(&mut ctx, selection, indexer_request).await;
+    let result = handle_indexer_query_inner(ctx, selection, indexer_request).await;
     METRICS.indexer_query.check(&[&deployment], &result);
 
     let (result, latest_block) = match result {
@@ -637,15 +693,25 @@ async fn handle_indexer_query(
         }) => (Err(error), latest_block),
     };
 
+    // The receipt fields are reported whenever a receipt was sent to the indexer
+    let receipt = Some(&selection.receipt);
+
     let latency_ms = ctx.response_time.as_millis() as u16;
     tracing::info!(
         target: INDEXER_REQUEST_TARGET,
         %deployment,
         url = %selection.url,
         blocks_behind = selection.blocks_behind,
-        fee_grt = (selection.receipt.grt_value() as f64 * 1e-18) as f32,
-        allocation = ?selection.receipt.allocation(),
-        legacy_scalar = matches!(&selection.receipt, ScalarReceipt::Legacy(_, _)),
+        seconds_behind = selection.seconds_behind,
+        freshness = ctx
+            .block_time
+            .map(|block_time| reports::freshness_score(selection.seconds_behind, block_time)),
+        fee_grt = receipt.map(|receipt| {
+            ((receipt.grt_value() + ctx.renewed_fees_grt) as f64 * 1e-18) as f32
+        }),
+        allocation = receipt.map(|receipt| tracing::field::debug(receipt.allocation())),
+        legacy_scalar = receipt.map(|receipt| matches!(receipt, ScalarReceipt::Legacy(_, _))),
+        payment_type = reports::payment_type(receipt),
         subgraph_chain = %ctx.deployment.manifest.network,
         response_time_ms = latency_ms,
         status_message = match &result {
@@ -677,15 +743,21 @@ impl From<IndexerError> for ExtendedIndexerError {
 
 async fn handle_indexer_query_inner(
     ctx: &mut IndexerQueryContext,
-    selection: &Selection,
+    selection: &mut Selection,
     indexer_request: String,
 ) -> Result<(ResponsePayload, Option<Block>), ExtendedIndexerError> {
     let start_time = Instant::now();
-    let result = ctx
+    let (result, renewed_fees_grt) = ctx
         .indexer_client
-        .query_indexer(selection, indexer_request.clone())
+        .query_indexer_with_retries(
+            ctx.receipt_signer,
+            selection,
+            indexer_request.clone(),
+            ctx.retries,
+        )
         .await;
     ctx.response_time = Instant::now() - start_time;
+    ctx.renewed_fees_grt = renewed_fees_grt;
 
     let deployment = selection.indexing.deployment.to_string();
     with_metric(&METRICS.indexer_query.duration, &[&deployment], |hist| {
@@ -696,6 +768,13 @@ async fn handle_indexer_query_inner(
     if response.status != StatusCode::OK.as_u16() {
         tracing::warn!(indexer_response_status = %response.status);
     }
+    tracing::info!(
+        target: INDEXER_REQUEST_TARGET,
+        http_status = response.status,
+        response_bytes = response.payload.body.len(),
+        ttfb_ms = response.timing.ttfb_ms,
+        body_ms = response.timing.body_ms,
+    );
 
     let (client_response, errors, block) = rewrite_response(&response.payload.body)?;
 
@@ -750,28 +829,15 @@ async fn handle_indexer_query_inner(
     }
 
     if let Some(attestation) = &response.payload.attestation {
-        let allocation = selection.receipt.allocation();
-        let verified = attestation::verify(
+        report_attestation(
+            ctx.kafka_client,
             ctx.attestation_domain,
             attestation,
-            &allocation,
-            &indexer_request,
-            &response.payload.body,
-        );
-        // We send the Kafka message directly to avoid passing the request & response payloads
-        // through the normal reporting path. This is to reduce log bloat.
-        let payload = serialize_attestation(
-            attestation,
-            allocation,
+            &selection.indexing.deployment,
+            selection.receipt.allocation(),
             indexer_request,
             response.payload.body,
-        );
-        ctx.kafka_client.send("gateway_attestations", &payload);
-        if let Err(err) = verified {
-            return Err(
-                IndexerError::BadResponse(anyhow!("bad attestation: {err}").to_string()).into(),
-            );
-        }
+        )?;
     }
 
     let client_response = ResponsePayload {
@@ -781,6 +847,58 @@ async fn handle_indexer_query_inner(
     Ok((client_response, block))
 }
 
+/// Verify the indexer's attestation, and report it to the `gateway_attestations` topic.
+///
+/// Attestations that fail verification, or attest to a deployment other than the one queried, are
+/// not reported, since they can't be used to dispute the indexer's response.
+fn report_attestation(
+    kafka_client: &KafkaClient,
+    attestation_domain: &Eip712Domain,
+    attestation: &Attestation,
+    deployment: &DeploymentId,
+    allocation: Address,
+    indexer_request: String,
+    response: String,
+) -> Result<(), IndexerError> {
+    METRICS.attestations.seen.inc();
+    if DeploymentId::from(attestation.deployment) != *deployment {
+        METRICS.attestations.dropped_invalid.inc();
+        return Err(IndexerError::BadResponse(
+            "bad attestation: deployment mismatch".to_string(),
+        ));
+    }
+    let verified = attestation::verify(
+        attestation_domain,
+        attestation,
+        &allocation,
+        &indexer_request,
+        &response,
+    );
+    if let Err(err) = verified {
+        METRICS.attestations.dropped_invalid.inc();
+        return Err(IndexerError::BadResponse(
+            anyhow!("bad attestation: {err}").to_string(),
+        ));
+    }
+    // We send the Kafka message directly to avoid passing the request & response payloads
+    // through the normal reporting path. This is to reduce log bloat.
+    let payload = serialize_attestation(attestation, allocation, indexer_request, response);
+    kafka_client.send("gateway_attestations", &payload);
+    METRICS.attestations.reported.inc();
+    Ok(())
+}
+
+/// Reject queries larger than the maximum query size, in bytes, if any.
+fn check_query_size(query: &str, max_query_bytes: Option<usize>) -> Result<(), Error> {
+    match max_query_bytes {
+        Some(max_query_bytes) if query.len() > max_query_bytes => Err(Error::BadQuery(anyhow!(
+            "query too large: {} bytes exceeds the limit of {max_query_bytes} bytes",
+            query.len()
+        ))),
+        _ => Ok(()),
+    }
+}
+
 pub fn indexer_fee(
     cost_model: &Option<Ptr<CostModel>>,
     context: &AgoraContext,
@@ -817,8 +935,8 @@ fn rewrite_response(
         hash: BlockHash,
         timestamp: Option<u64>,
     }
-    let mut payload: GQLResponseBody<ProbedData> =
-        serde_json::from_str(response).map_err(|err| IndexerError::BadResponse(err.to_string()))?;
+    let mut payload: GQLResponseBody<ProbedData> = serde_json::from_str(response)
+        .map_err(|err| IndexerError::UnexpectedPayload(err.to_string()))?;
 
     // Avoid processing oversized errors.
     for err in &mut payload.errors {
@@ -843,6 +961,199 @@ fn rewrite_response(
 
 #[cfg(test)]
 mod tests {
+    use std::{
+        collections::{BTreeSet, HashMap, HashSet},
+        sync::Arc,
+    };
+
+    use alloy_primitives::{keccak256, Address, B256, U256};
+    use assert_matches::assert_matches;
+    use axum::http::{HeaderMap, HeaderName, HeaderValue};
+    use eventuals::{Eventual, Ptr};
+    use gateway_framework::{
+        errors::{Error, IndexerError},
+        reporting::{KafkaClient, METRICS},
+        topology::network::{Deployment, GraphNetwork, Manifest, Subgraph},
+    };
+    use thegraph_core::types::{
+        attestation::{self, Attestation},
+        DeploymentId, SubgraphId,
+    };
+
+    use super::{
+        check_query_size, client_region, report_attestation, resolve_subgraph_deployments,
+        QuerySelector, CLIENT_REGION_HEADER,
+    };
+
+    #[test]
+    fn client_region_from_headers() {
+        let headers = |region: &'static str| {
+            HeaderMap::from_iter([(
+                HeaderName::from_static(CLIENT_REGION_HEADER),
+                HeaderValue::from_static(region),
+            )])
+        };
+
+        assert_eq!(client_region(&headers("de")), Some("DE".to_string()));
+        assert_eq!(client_region(&headers("XX")), None);
+        assert_eq!(client_region(&HeaderMap::new()), None);
+    }
+
+    #[test]
+    fn query_size_limit() {
+        //* Given
+        let query = "{ _meta { block { number } } }";
+        let max_query_bytes = query.len();
+
+        //* Then
+        assert_matches!(check_query_size(query, Some(max_query_bytes)), Ok(()));
+        assert_matches!(
+            check_query_size(query, Some(max_query_bytes - 1)),
+            Err(Error::BadQuery(_))
+        );
+        assert_matches!(check_query_size(query, None), Ok(()));
+    }
+
+    #[test]
+    fn invalid_attestation_is_not_reported() {
+        //* Given
+        let kafka_client = KafkaClient::new(&rdkafka::ClientConfig::new()).unwrap();
+        let domain = attestation::eip712_domain(U256::from(1), Address::default());
+        let request = r#"{"query":"{ _meta { block { number } } }"}"#;
+        let response = r#"{"data":{}}"#;
+        // The attested CIDs match the request & response, but the attestation is not signed by
+        // the allocation
+        let attestation = Attestation {
+            request_cid: keccak256(request),
+            response_cid: keccak256(response),
+            deployment: B256::repeat_byte(0x42),
+            r: B256::repeat_byte(0x01),
+            s: B256::repeat_byte(0x02),
+            v: 27,
+        };
+        let dropped_invalid = METRICS.attestations.dropped_invalid.get();
+        let reported = METRICS.attestations.reported.get();
+
+        //* When
+        let result = report_attestation(
+            &kafka_client,
+            &domain,
+            &attestation,
+            &DeploymentId::from(attestation.deployment),
+            Address::repeat_byte(0x03),
+            request.to_string(),
+            response.to_string(),
+        );
+
+        //* Then
+        assert_matches!(result, Err(IndexerError::BadResponse(message)) => {
+            assert!(message.starts_with("bad attestation:"));
+        });
+        assert_eq!(
+            METRICS.attestations.dropped_invalid.get(),
+            dropped_invalid + 1
+        );
+        assert_eq!(METRICS.attestations.reported.get(), reported);
+    }
+
+    #[test]
+    fn attestation_deployment_mismatch_is_not_reported() {
+        //* Given
+        let kafka_client = KafkaClient::new(&rdkafka::ClientConfig::new()).unwrap();
+        let domain = attestation::eip712_domain(U256::from(1), Address::default());
+        let attestation = Attestation {
+            request_cid: B256::ZERO,
+            response_cid: B256::ZERO,
+            deployment: B256::repeat_byte(0x42),
+            r: B256::ZERO,
+            s: B256::ZERO,
+            v: 27,
+        };
+        let reported = METRICS.attestations.reported.get();
+
+        //* When
+        let result = report_attestation(
+            &kafka_client,
+            &domain,
+            &attestation,
+            &DeploymentId::from(B256::repeat_byte(0x43)),
+            Address::repeat_byte(0x03),
+            "{}".to_string(),
+            r#"{"data":{}}"#.to_string(),
+        );
+
+        //* Then
+        assert_matches!(result, Err(IndexerError::BadResponse(message)) => {
+            assert_eq!(message, "bad attestation: deployment mismatch");
+        });
+        assert_eq!(METRICS.attestations.reported.get(), reported);
+    }
+
+    #[test]
+    fn blocked_deployments_are_not_resolved() {
+        //* Given
+        let subgraph_id: SubgraphId = "EMRitnR1t3drKrDQSmJMSmHBPB2sGotgZE12DzWNezDn"
+            .parse()
+            .unwrap();
+        let deployment = |id: &str| {
+            Arc::new(Deployment {
+                id: id.parse().unwrap(),
+                manifest: Manifest {
+                    network: "mainnet".to_string(),
+                    min_block: 0,
+                },
+                indexers: Default::default(),
+                subgraphs: BTreeSet::from([subgraph_id]),
+                transferred_to_l2: false,
+            })
+        };
+        let old_version = deployment("QmQqLJVgZLcRduoszARzRi12qGheUTWAHFf3ixMeGm2xML");
+        let new_version = deployment("QmUzRg2HHMpbgf6Q4VHKNDbtBEJnyp5JWCh2gUX9AV6jXv");
+        let subgraph = Subgraph {
+            deployments: vec![old_version.clone(), new_version.clone()],
+            id: subgraph_id,
+            l2_id: None,
+        };
+        let network = GraphNetwork {
+            subgraphs: Eventual::from_value(Ptr::new(HashMap::from([(subgraph_id, subgraph)]))),
+            deployments: Eventual::from_value(Ptr::new(HashMap::from([
+                (old_version.id, old_version.clone()),
+                (new_version.id, new_version.clone()),
+            ]))),
+            indexers: Eventual::from_value(Ptr::default()),
+        };
+        let blocked_deployments: HashSet<DeploymentId> = HashSet::from([new_version.id]);
+
+        //* When
+        let by_subgraph = resolve_subgraph_deployments(
+            &network,
+            &blocked_deployments,
+            &QuerySelector::Subgraph(subgraph_id),
+        );
+        let by_blocked_deployment = resolve_subgraph_deployments(
+            &network,
+            &blocked_deployments,
+            &QuerySelector::Deployment(new_version.id),
+        );
+        let by_deployment = resolve_subgraph_deployments(
+            &network,
+            &blocked_deployments,
+            &QuerySelector::Deployment(old_version.id),
+        );
+
+        //* Then
+        assert_matches!(by_subgraph, Ok((deployments, Some(_))) => {
+            assert_eq!(
+                deployments.iter().map(|d| d.id).collect::<Vec<_>>(),
+                vec![old_version.id]
+            );
+        });
+        assert_matches!(by_blocked_deployment, Err(Error::SubgraphNotFound(_)));
+        assert_matches!(by_deployment, Ok((deployments, None)) => {
+            assert_eq!(deployments.len(), 1);
+        });
+    }
+
     mod require_req_auth {
         use std::{collections::HashMap, sync::Arc};
 
@@ -869,8 +1180,10 @@ mod tests {
                 payment_required: false,
                 api_keys: watch::channel(Default::default()).1,
                 special_api_keys: Default::default(),
+                api_key_rate_limit: None,
                 special_query_key_signers: Default::default(),
                 subscriptions: watch::channel(Default::default()).1,
+                subscriptions_max_staleness: Default::default(),
                 subscription_rate_per_query: 0,
                 subscription_domains: Default::default(),
             };
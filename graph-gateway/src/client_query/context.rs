@@ -1,18 +1,23 @@
-use std::collections::{HashMap, HashSet};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::Duration,
+};
 
 use alloy_primitives::Address;
 use alloy_sol_types::Eip712Domain;
 use eventuals::{Eventual, Ptr};
 use gateway_common::types::Indexing;
 use gateway_framework::{
-    budgets::Budgeter,
+    budgets::{Budgeter, FeeRounding},
     chains::Chains,
-    network::{discovery::Status, indexing_performance::IndexingPerformance},
+    network::{
+        discovery::Status, exchange_rate::GrtPerUsd, indexing_performance::IndexingPerformance,
+    },
     reporting::KafkaClient,
     scalar::ReceiptSigner,
     topology::network::GraphNetwork,
 };
-use ordered_float::NotNan;
+use thegraph_core::types::DeploymentId;
 use tokio::sync::watch;
 use url::Url;
 
@@ -22,12 +27,23 @@ use crate::indexer_client::IndexerClient;
 pub struct Context {
     pub indexer_client: IndexerClient,
     pub receipt_signer: &'static ReceiptSigner,
+    /// Number of times a failed query is retried on the same indexer.
+    pub indexer_query_retries: usize,
     pub kafka_client: &'static KafkaClient,
     pub budgeter: &'static Budgeter,
+    /// Rounding applied to fractional indexer fees before signing receipts.
+    pub fee_rounding: FeeRounding,
     pub l2_gateway: Option<Url>,
-    pub grt_per_usd: watch::Receiver<NotNan<f64>>,
+    /// Maximum size of a client GraphQL query, in bytes.
+    pub max_query_bytes: Option<usize>,
+    pub grt_per_usd: watch::Receiver<GrtPerUsd>,
+    /// Maximum age of the exchange rate before the USD fees it produces are reported as stale.
+    pub grt_per_usd_max_age: Duration,
     pub chains: &'static Chains,
+    pub chain_block_times: &'static BTreeMap<String, Duration>,
     pub network: GraphNetwork,
+    /// Deployments excluded from the network topology. Queries never resolve to these.
+    pub blocked_deployments: &'static HashSet<DeploymentId>,
     pub indexing_statuses: Eventual<Ptr<HashMap<Indexing, Status>>>,
     pub indexing_perf: IndexingPerformance,
     pub attestation_domain: &'static Eip712Domain,
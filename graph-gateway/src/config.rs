@@ -10,6 +10,7 @@ use alloy_primitives::{Address, BlockNumber, U256};
 use custom_debug::CustomDebug;
 use gateway_framework::{
     auth::methods::api_keys::APIKey,
+    budgets::FeeRounding,
     config::{Hidden, HiddenSecretKey},
 };
 use secp256k1::SecretKey;
@@ -24,26 +25,51 @@ use url::Url;
 pub struct Config {
     #[serde(default)]
     pub api_keys: Option<ApiKeys>,
+    /// Per-API key rate limit (default: no limit)
+    pub api_key_rate_limit: Option<ApiKeyRateLimit>,
     pub attestations: AttestationConfig,
     /// List of indexer addresses to block. This should only be used temprorarily, to compensate for
     /// indexer-selection imperfections.
     #[serde(default)]
     pub bad_indexers: Vec<Address>,
-    /// Chain aliases
+    /// List of deployments to exclude from the network topology. Client queries are never resolved
+    /// to these deployments.
+    #[serde(default)]
+    pub blocked_deployments: Vec<DeploymentId>,
+    /// Chain aliases, mapping the chain names found in subgraph manifests to their canonical name
     #[serde(default)]
     pub chain_aliases: BTreeMap<String, String>,
+    /// Expected block time per chain, in milliseconds. Used to normalize the seconds an indexer is
+    /// behind chain head into a freshness score for the indexer attempt reports.
+    #[serde(default)]
+    pub chain_block_times_ms: BTreeMap<String, u64>,
     /// Ethereum RPC provider, or fixed exchange rate for testing
     pub exchange_rate_provider: ExchangeRateProvider,
+    /// Maximum age of the GRT/USD exchange rate, in seconds, before the USD figures it produces
+    /// are flagged as stale in client query reports (default: 300 seconds)
+    pub exchange_rate_max_age: Option<u64>,
+    /// Rounding applied to fractional indexer fees before signing receipts: `floor`, `ceil` or
+    /// `nearest` (default: floor)
+    #[serde(default)]
+    pub fee_rounding: FeeRounding,
     /// The Gateway unique identifier. This ID is used to identify the Gateway in the network
     /// and traceability purposes.
     ///
     /// If not provided a UUID is generated.
     #[serde(default)]
     pub gateway_id: Option<String>,
-    /// Graph network environment identifier, inserted into Kafka messages
+    /// Graph network environment identifier, inserted into Kafka messages. Must not be empty.
+    #[serde(deserialize_with = "deserialize_not_empty")]
     pub graph_env_id: String,
     /// File path of CSV containing rows of `IpNetwork,Country`
     pub ip_blocker_db: Option<PathBuf>,
+    /// Path prefix of the indexers' query endpoint, for indexers behind a reverse proxy
+    /// (default: none)
+    #[serde(default)]
+    pub indexer_base_path: String,
+    /// Number of times a failed query is retried on the same indexer (default: 0)
+    #[serde(default)]
+    pub indexer_query_retries: usize,
     /// IP rate limit in requests per second
     pub ip_rate_limit: u16,
     /// See https://github.com/confluentinc/librdkafka/blob/master/CONFIGURATION.md
@@ -51,6 +77,8 @@ pub struct Config {
     pub kafka: KafkaConfig,
     /// Format log output as JSON
     pub log_json: bool,
+    /// Maximum size of a client GraphQL query, in bytes (default: no limit)
+    pub max_query_bytes: Option<usize>,
     /// L2 gateway to forward client queries to
     #[debug(with = fmt_optional_url)]
     #[serde_as(as = "Option<DisplayFromStr>")]
@@ -84,6 +112,17 @@ pub struct Config {
     pub subscriptions: Option<Subscriptions>,
 }
 
+fn deserialize_not_empty<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let value = String::deserialize(deserializer)?;
+    if value.trim().is_empty() {
+        return Err(serde::de::Error::custom("must not be empty"));
+    }
+    Ok(value)
+}
+
 fn fmt_optional_url(url: &Option<Url>, f: &mut fmt::Formatter) -> fmt::Result {
     match url {
         Some(url) => write!(f, "Some({})", url),
@@ -109,6 +148,14 @@ pub enum ApiKeys {
     Fixed(Vec<APIKey>),
 }
 
+#[derive(Debug, Deserialize)]
+pub struct ApiKeyRateLimit {
+    /// Token refill rate, in queries per second
+    pub rate: f64,
+    /// Maximum number of queries allowed in a burst
+    pub burst: u32,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct AttestationConfig {
     pub chain_id: String,
@@ -125,8 +172,43 @@ pub enum ExchangeRateProvider {
     Fixed(f64),
 }
 
+/// The Kafka producer configuration.
 #[derive(Debug, Deserialize)]
-pub struct KafkaConfig(BTreeMap<String, String>);
+pub struct KafkaConfig {
+    /// The compression codec of the produced messages. Takes precedence over the
+    /// `compression.codec` setting.
+    #[serde(default)]
+    compression: CompressionCodec,
+    /// The librdkafka settings.
+    #[serde(flatten)]
+    settings: BTreeMap<String, String>,
+}
+
+/// The Kafka message compression codec.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CompressionCodec {
+    /// Use the librdkafka `compression.codec` setting, uncompressed by default.
+    #[default]
+    None,
+    Gzip,
+    Snappy,
+    Lz4,
+    Zstd,
+}
+
+impl CompressionCodec {
+    /// The librdkafka `compression.codec` value of the codec.
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Gzip => "gzip",
+            Self::Snappy => "snappy",
+            Self::Lz4 => "lz4",
+            Self::Zstd => "zstd",
+        }
+    }
+}
 
 impl Default for KafkaConfig {
     fn default() -> Self {
@@ -137,24 +219,28 @@ impl Default for KafkaConfig {
             ("queue.buffering.max.ms", "1000"),
             ("queue.buffering.max.messages", "100000"),
         ];
-        Self(
-            settings
+        Self {
+            compression: CompressionCodec::default(),
+            settings: settings
                 .into_iter()
                 .map(|(k, v)| (k.to_owned(), v.to_owned()))
                 .collect(),
-        )
+        }
     }
 }
 
 impl From<KafkaConfig> for rdkafka::config::ClientConfig {
     fn from(mut from: KafkaConfig) -> Self {
-        let mut settings = KafkaConfig::default().0;
-        settings.append(&mut from.0);
+        let mut settings = KafkaConfig::default().settings;
+        settings.append(&mut from.settings);
 
         let mut config = rdkafka::config::ClientConfig::new();
         for (k, v) in settings {
             config.set(&k, &v);
         }
+        if from.compression != CompressionCodec::None {
+            config.set("compression.codec", from.compression.as_str());
+        }
         config
     }
 }
@@ -194,6 +280,12 @@ pub struct Subscriptions {
     /// Allow startup with no active subscriptions.
     #[serde(default)]
     pub allow_empty: bool,
+    /// Maximum age of the last-known subscriptions, in seconds, before they are no longer served
+    /// when the subscriptions subgraph is unreachable (default: 600 seconds)
+    pub max_staleness: Option<u64>,
+    /// Maximum number of authorized signers per user, including the user itself. Signers beyond
+    /// the limit are ignored (default: 100)
+    pub max_signers: Option<usize>,
 }
 
 #[serde_as]
@@ -215,3 +307,49 @@ pub struct ProofOfIndexingInfo {
     /// POI block number.
     pub block_number: BlockNumber,
 }
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::KafkaConfig;
+
+    #[derive(Debug, Deserialize)]
+    struct GraphEnv {
+        #[serde(deserialize_with = "super::deserialize_not_empty")]
+        graph_env_id: String,
+    }
+
+    #[test]
+    fn reject_empty_graph_env_id() {
+        //* When
+        let empty = serde_json::from_str::<GraphEnv>(r#"{"graph_env_id": ""}"#);
+        let blank = serde_json::from_str::<GraphEnv>(r#"{"graph_env_id": "  "}"#);
+        let valid = serde_json::from_str::<GraphEnv>(r#"{"graph_env_id": "mainnet"}"#);
+
+        //* Then
+        assert!(empty.is_err_and(|err| err.to_string().contains("must not be empty")));
+        assert!(blank.is_err());
+        assert_eq!(valid.unwrap().graph_env_id, "mainnet");
+    }
+
+    #[test]
+    fn kafka_producer_config_carries_compression_codec() {
+        //* Given
+        let config = serde_json::from_str::<KafkaConfig>(
+            r#"{"compression": "zstd", "bootstrap.servers": "localhost:9092"}"#,
+        )
+        .unwrap();
+        let uncompressed = serde_json::from_str::<KafkaConfig>("{}").unwrap();
+
+        //* When
+        let config = rdkafka::config::ClientConfig::from(config);
+        let uncompressed = rdkafka::config::ClientConfig::from(uncompressed);
+
+        //* Then
+        assert_eq!(config.get("compression.codec"), Some("zstd"));
+        assert_eq!(config.get("bootstrap.servers"), Some("localhost:9092"));
+        assert_eq!(config.get("compression"), None);
+        assert_eq!(uncompressed.get("compression.codec"), None);
+    }
+}
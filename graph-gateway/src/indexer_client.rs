@@ -1,5 +1,11 @@
+use std::time::{Duration, Instant};
+
 use alloy_primitives::BlockNumber;
-use gateway_framework::errors::{IndexerError, UnavailableReason::*};
+use gateway_framework::{
+    errors::{IndexerError, UnavailableReason::*},
+    reporting::{with_metric, METRICS},
+    scalar::ReceiptSigner,
+};
 use serde::Deserialize;
 use thegraph_core::types::attestation::Attestation;
 
@@ -8,6 +14,32 @@ use crate::client_query::Selection;
 pub struct IndexerResponse {
     pub status: u16,
     pub payload: ResponsePayload,
+    pub timing: TimingBreakdown,
+}
+
+/// The breakdown of an indexer request's response time, in milliseconds.
+///
+/// reqwest does not expose the DNS resolution, connection and TLS handshake times of a request, so
+/// these are included in the time to first byte. Requests over a pooled connection skip them.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct TimingBreakdown {
+    /// Time from sending the request until the response headers are received.
+    pub ttfb_ms: u32,
+    /// Time spent receiving and decoding the response body.
+    pub body_ms: u32,
+    /// Total response time.
+    pub total_ms: u32,
+}
+
+impl TimingBreakdown {
+    fn new(start: Instant, headers_received: Instant, end: Instant) -> Self {
+        let ms = |duration: Duration| duration.as_millis() as u32;
+        Self {
+            ttfb_ms: ms(headers_received - start),
+            body_ms: ms(end - headers_received),
+            total_ms: ms(end - start),
+        }
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -22,6 +54,21 @@ pub struct BlockError {
     pub latest_block: Option<BlockNumber>,
 }
 
+impl BlockError {
+    /// The number of blocks the indexer is behind the block required by the query, if both are
+    /// known.
+    pub fn blocks_behind(&self) -> Option<u64> {
+        Some(self.unresolved?.saturating_sub(self.latest_block?))
+    }
+
+    /// Estimate how far behind the block required by the query the indexer is, in seconds, given
+    /// the chain's block time.
+    pub fn seconds_behind(&self, block_time: Duration) -> Option<u32> {
+        let blocks_behind = self.blocks_behind()?;
+        Some((blocks_behind as f64 * block_time.as_secs_f64()).ceil() as u32)
+    }
+}
+
 #[derive(Debug, Deserialize)]
 pub struct IndexerResponsePayload {
     #[serde(rename = "graphQLResponse")]
@@ -30,20 +77,200 @@ pub struct IndexerResponsePayload {
     pub error: Option<String>,
 }
 
+/// The default maximum time to wait for an indexer response.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(20);
+
+/// The default time to keep idle connections to indexers open.
+pub const DEFAULT_POOL_IDLE_TIMEOUT: Duration = Duration::from_secs(90);
+
 #[derive(Clone)]
 pub struct IndexerClient {
     pub client: reqwest::Client,
+    /// If true, responses without an attestation are rejected with
+    /// [`IndexerError::NoAttestation`].
+    pub require_attestation: bool,
+    /// Path prefix prepended to the deployment query path (e.g., `proxy/`). Either empty, or
+    /// without a leading slash and with a single trailing slash.
+    pub base_path: String,
+}
+
+/// The [`IndexerClient`] builder.
+pub struct IndexerClientBuilder {
+    timeout: Duration,
+    pool_max_idle_per_host: usize,
+    pool_idle_timeout: Option<Duration>,
+    tcp_keepalive: Option<Duration>,
+    require_attestation: bool,
+    base_path: String,
+}
+
+impl Default for IndexerClientBuilder {
+    fn default() -> Self {
+        Self {
+            timeout: DEFAULT_TIMEOUT,
+            pool_max_idle_per_host: usize::MAX,
+            pool_idle_timeout: Some(DEFAULT_POOL_IDLE_TIMEOUT),
+            tcp_keepalive: None,
+            require_attestation: false,
+            base_path: String::new(),
+        }
+    }
+}
+
+impl IndexerClientBuilder {
+    /// Sets the maximum time to wait for an indexer response.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sets the maximum number of idle connections kept open per indexer host.
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> Self {
+        self.pool_max_idle_per_host = max;
+        self
+    }
+
+    /// Sets the time to keep idle connections open. If `None`, idle connections are never closed.
+    pub fn with_pool_idle_timeout(mut self, timeout: Option<Duration>) -> Self {
+        self.pool_idle_timeout = timeout;
+        self
+    }
+
+    /// Sets the interval of TCP keep-alive probes. If `None`, keep-alive probes are disabled.
+    pub fn with_tcp_keepalive(mut self, interval: Option<Duration>) -> Self {
+        self.tcp_keepalive = interval;
+        self
+    }
+
+    /// Sets whether responses without an attestation should be rejected.
+    pub fn with_require_attestation(mut self, require_attestation: bool) -> Self {
+        self.require_attestation = require_attestation;
+        self
+    }
+
+    /// Sets the path prefix of the indexers' query endpoint, for indexers behind a reverse proxy
+    /// (e.g., `/proxy` sends queries to `/proxy/subgraphs/id/...`).
+    pub fn with_base_path(mut self, base_path: impl AsRef<str>) -> Self {
+        self.base_path = normalize_base_path(base_path.as_ref());
+        self
+    }
+
+    /// Builds the [`IndexerClient`] instance.
+    pub fn build(self) -> IndexerClient {
+        let client = reqwest::Client::builder()
+            .timeout(self.timeout)
+            .pool_max_idle_per_host(self.pool_max_idle_per_host)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.tcp_keepalive)
+            .build()
+            .expect("failed to create indexer HTTP client");
+        IndexerClient {
+            client,
+            require_attestation: self.require_attestation,
+            base_path: self.base_path,
+        }
+    }
+}
+
+/// Normalizes a path prefix so that joining it with a relative path never produces empty segments.
+fn normalize_base_path(base_path: &str) -> String {
+    let segments = base_path
+        .split('/')
+        .filter(|segment| !segment.is_empty())
+        .collect::<Vec<_>>();
+    if segments.is_empty() {
+        return String::new();
+    }
+    format!("{}/", segments.join("/"))
 }
 
 impl IndexerClient {
+    /// Creates a new [`IndexerClientBuilder`] instance.
+    pub fn builder() -> IndexerClientBuilder {
+        IndexerClientBuilder::default()
+    }
+
     pub async fn query_indexer(
         &self,
         selection: &Selection,
         query: String,
     ) -> Result<IndexerResponse, IndexerError> {
+        let indexer = format!("{:?}", selection.indexing.indexer);
+        let timer = with_metric(&METRICS.indexer_client.duration, &[&indexer], |h| {
+            h.start_timer()
+        });
+        let result = self.send_query(selection, query).await;
+        drop(timer);
+
+        if let Err(err) = &result {
+            with_metric(
+                &METRICS.indexer_client.errors,
+                &[&indexer, err.label()],
+                |c| c.inc(),
+            );
+        }
+        result
+    }
+
+    /// Query the indexer, retrying up to `retries` times on transient failures.
+    ///
+    /// If an attempt fails to connect to the indexer or times out, the indexer did not accept the
+    /// receipt, so the same receipt is sent again and no fee is spent on the failed attempt.
+    /// Otherwise, the receipt is renewed before the next attempt. The selection's receipt is
+    /// updated in place, so it holds the receipt of the last attempt.
+    ///
+    /// Returns the query result along with the fees of the renewed receipts, in GRT wei.
+    pub async fn query_indexer_with_retries(
+        &self,
+        receipt_signer: &ReceiptSigner,
+        selection: &mut Selection,
+        query: String,
+        retries: usize,
+    ) -> (Result<IndexerResponse, IndexerError>, u128) {
+        let mut renewed_fees: u128 = 0;
+        let mut attempt = 0;
+        loop {
+            let err = match self.query_indexer(selection, query.clone()).await {
+                Ok(response) => return (Ok(response), renewed_fees),
+                Err(err) if attempt == retries || !is_transient_error(&err) => {
+                    return (Err(err), renewed_fees)
+                }
+                Err(err) => err,
+            };
+            attempt += 1;
+
+            if !is_receipt_unaccepted_error(&err) {
+                selection.receipt = match receipt_signer
+                    .renew_receipt(&selection.indexing, &selection.receipt)
+                    .await
+                {
+                    Some(receipt) => receipt,
+                    None => {
+                        return (
+                            Err(IndexerError::Internal("failed to create receipt")),
+                            renewed_fees,
+                        )
+                    }
+                };
+                renewed_fees += selection.receipt.grt_value();
+            }
+        }
+    }
+
+    /// Sends the body to the given path, relative to the selected indexer's URL, with the
+    /// selection's receipt attached.
+    ///
+    /// This is the lower-level request used by [`IndexerClient::query_indexer`], it can target any
+    /// of the indexer's endpoints (e.g., `status` or `cost`).
+    pub async fn query_path(
+        &self,
+        selection: &Selection,
+        path: &str,
+        body: String,
+    ) -> Result<reqwest::Response, IndexerError> {
         let url = selection
             .url
-            .join(&format!("subgraphs/id/{:?}", selection.indexing.deployment))
+            .join(path)
             .map_err(|_| IndexerError::Unavailable(NoStatus))?;
 
         let result = self
@@ -51,46 +278,123 @@ impl IndexerClient {
             .post(url)
             .header("Content-Type", "application/json")
             .header("Scalar-Receipt", &selection.receipt.serialize())
-            .body(query)
+            .body(body)
             .send()
-            .await
-            .and_then(|response| response.error_for_status());
-
-        let response = match result {
-            Ok(response) => response,
-            Err(err) if err.is_timeout() => return Err(IndexerError::Timeout),
-            Err(err) => match err.status() {
-                Some(status) => return Err(IndexerError::BadResponse(status.as_u16().to_string())),
-                _ if err.is_connect() => {
-                    return Err(IndexerError::BadResponse("failed to connect".to_string()))
-                }
-                _ => return Err(IndexerError::BadResponse(err.to_string())),
-            },
-        };
+            .await;
+
+        match result {
+            Ok(response) => Ok(response),
+            Err(err) if err.is_timeout() => Err(IndexerError::Timeout),
+            Err(err) if err.is_connect() => Err(IndexerError::Unavailable(Unreachable)),
+            Err(err) => Err(IndexerError::BadResponse(err.to_string())),
+        }
+    }
+
+    async fn send_query(
+        &self,
+        selection: &Selection,
+        query: String,
+    ) -> Result<IndexerResponse, IndexerError> {
+        let path = format!(
+            "{}subgraphs/id/{:?}",
+            self.base_path, selection.indexing.deployment
+        );
+
+        let start = Instant::now();
+        let response = self.query_path(selection, &path, query).await?;
+        let headers_received = Instant::now();
         let response_status = response.status();
+        let status_class = format!("{}xx", response_status.as_u16() / 100);
+        with_metric(
+            &METRICS.indexer_client.status,
+            &[&format!("{:?}", selection.indexing.indexer), &status_class],
+            |c| c.inc(),
+        );
+        if !response_status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            if is_no_allocation_error(response_status.as_u16(), &body) {
+                return Err(IndexerError::Unavailable(NoAllocation));
+            }
+            if response_status.is_server_error() {
+                return Err(IndexerError::Unavailable(ServerError));
+            }
+            return Err(IndexerError::BadResponse(
+                response_status.as_u16().to_string(),
+            ));
+        }
         let payload = response
             .json::<IndexerResponsePayload>()
             .await
-            .map_err(|err| IndexerError::BadResponse(err.to_string()))?;
-        let graphql_response = match payload.graphql_response {
-            Some(graphql_response) => graphql_response,
-            None => {
-                let err = payload
-                    .error
-                    .unwrap_or_else(|| "missing GraphQL response".to_string());
+            .map_err(|err| {
+                if err.is_decode() {
+                    IndexerError::UnexpectedPayload(err.to_string())
+                } else {
+                    IndexerError::BadResponse(err.to_string())
+                }
+            })?;
+        let timing = TimingBreakdown::new(start, headers_received, Instant::now());
+        let graphql_response = match (payload.graphql_response, payload.error) {
+            (Some(graphql_response), _) => graphql_response,
+            (None, Some(err)) => {
+                if is_no_allocation_error(response_status.as_u16(), &err) {
+                    return Err(IndexerError::Unavailable(NoAllocation));
+                }
                 return Err(IndexerError::BadResponse(err));
             }
+            (None, None) => {
+                return Err(IndexerError::UnexpectedPayload(
+                    "missing GraphQL response".to_string(),
+                ))
+            }
         };
+        if self.require_attestation && payload.attestation.is_none() {
+            return Err(IndexerError::NoAttestation);
+        }
         Ok(IndexerResponse {
             status: response_status.as_u16(),
             payload: ResponsePayload {
                 body: graphql_response,
                 attestation: payload.attestation,
             },
+            timing,
         })
     }
 }
 
+/// Returns true if the error is transient, i.e., the same query to the same indexer may succeed
+/// if retried.
+fn is_transient_error(err: &IndexerError) -> bool {
+    matches!(
+        err,
+        IndexerError::Timeout | IndexerError::Unavailable(Unreachable | ServerError)
+    )
+}
+
+/// Returns true if the request failed before the indexer accepted its receipt, i.e., the
+/// connection to the indexer failed or the request timed out.
+fn is_receipt_unaccepted_error(err: &IndexerError) -> bool {
+    matches!(
+        err,
+        IndexerError::Timeout | IndexerError::Unavailable(Unreachable)
+    )
+}
+
+/// Returns true if the indexer rejected the query because the receipt does not reference a valid
+/// allocation for the deployment. Other payment errors (e.g. invalid signatures or insufficient
+/// fees) are not considered allocation errors.
+pub fn is_no_allocation_error(status: u16, err: &str) -> bool {
+    // Indexers respond with `400 Bad Request` or `402 Payment Required`, depending on the version
+    // of indexer-service.
+    if !matches!(status, 200 | 400 | 402) {
+        return false;
+    }
+    let err = err.to_ascii_lowercase();
+    err.contains("no valid allocation")
+        || err.contains("no allocation")
+        || err.contains("allocation not found")
+        || err.contains("unknown allocation")
+}
+
 pub fn check_block_error(err: &str) -> Result<(), BlockError> {
     // TODO: indexers should *always* report their block status in a header on every query. This
     // will significantly reduce how brittle this feedback is, and also give a stronger basis for
@@ -111,7 +415,55 @@ pub fn check_block_error(err: &str) -> Result<(), BlockError> {
 
 #[cfg(test)]
 mod test {
-    use crate::indexer_client::BlockError;
+    use std::{
+        collections::{HashMap, HashSet},
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Duration,
+    };
+
+    use alloy_primitives::{Address, U256};
+    use assert_matches::assert_matches;
+    use axum::{
+        extract::{ConnectInfo, OriginalUri, State},
+        http::{HeaderMap, StatusCode},
+        Router,
+    };
+    use gateway_common::types::Indexing;
+    use gateway_framework::{
+        errors::{IndexerError, UnavailableReason},
+        reporting::METRICS,
+        scalar::{ReceiptSigner, ScalarReceipt},
+        test_utils::serve,
+    };
+    use secp256k1::SecretKey;
+    use tokio::net::TcpListener;
+    use url::Url;
+
+    use super::{normalize_base_path, IndexerClient};
+    use crate::{client_query::Selection, indexer_client::BlockError};
+
+    fn test_selection(url: Url) -> Selection {
+        test_indexer_selection(Address::default(), url)
+    }
+
+    fn test_indexer_selection(indexer: Address, url: Url) -> Selection {
+        Selection {
+            indexing: Indexing {
+                indexer,
+                deployment: "QmQqLJVgZLcRduoszARzRi12qGheUTWAHFf3ixMeGm2xML"
+                    .parse()
+                    .unwrap(),
+            },
+            url,
+            receipt: ScalarReceipt::Legacy(0, vec![0; 164]),
+            blocks_behind: 0,
+            seconds_behind: 0,
+        }
+    }
 
     #[test]
     fn check_block_error() {
@@ -130,4 +482,480 @@ mod test {
             assert_eq!(super::check_block_error(input), expected);
         }
     }
+
+    #[test]
+    fn block_error_time_behind() {
+        //* Given
+        let complete = BlockError {
+            unresolved: Some(133239697),
+            latest_block: Some(133239690),
+        };
+        let incomplete = BlockError {
+            unresolved: Some(133239697),
+            latest_block: None,
+        };
+        let block_time = Duration::from_millis(250);
+
+        //* Then
+        assert_eq!(complete.blocks_behind(), Some(7));
+        assert_eq!(complete.seconds_behind(block_time), Some(2));
+        assert_eq!(incomplete.blocks_behind(), None);
+        assert_eq!(incomplete.seconds_behind(block_time), None);
+    }
+
+    #[test]
+    fn is_no_allocation_error() {
+        let tests = [
+            (200, "", false),
+            (
+                402,
+                "No valid allocation for receipt: 0x89b23fea4e46d40e8a4c6cca723e2a03fdd4bec2",
+                true,
+            ),
+            (
+                400,
+                r#"{"message":"Allocation not found: 0x89b23fea4e46d40e8a4c6cca723e2a03fdd4bec2"}"#,
+                true,
+            ),
+            (
+                200,
+                "Unknown allocation 0x89b23fea4e46d40e8a4c6cca723e2a03fdd4bec2",
+                true,
+            ),
+            (402, "Invalid receipt signature", false),
+            (402, "Receipt value below minimum fee", false),
+            (500, "No valid allocation", false),
+        ];
+        for (status, err, expected) in tests {
+            assert_eq!(
+                super::is_no_allocation_error(status, err),
+                expected,
+                "{err}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn query_indexer_metrics() {
+        //* Given
+        let ok =
+            serve(Router::new().fallback(|| async { r#"{"graphQLResponse":"{\"data\":{}}"}"# }))
+                .await;
+        let stalled = serve(Router::new().fallback(|| async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            r#"{"graphQLResponse":"{\"data\":{}}"}"#
+        }))
+        .await;
+
+        let client = IndexerClient {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_millis(100))
+                .build()
+                .unwrap(),
+            require_attestation: false,
+            base_path: String::new(),
+        };
+        let ok_indexer = Address::repeat_byte(0x38);
+        let stalled_indexer = Address::repeat_byte(0x39);
+        let ok_label = format!("{ok_indexer:?}");
+        let stalled_label = format!("{stalled_indexer:?}");
+
+        //* When
+        let ok_result = client
+            .query_indexer(&test_indexer_selection(ok_indexer, ok), "{}".to_string())
+            .await;
+        let stalled_result = client
+            .query_indexer(
+                &test_indexer_selection(stalled_indexer, stalled),
+                "{}".to_string(),
+            )
+            .await;
+
+        //* Then
+        assert_matches!(ok_result, Ok(_));
+        assert_matches!(stalled_result, Err(IndexerError::Timeout));
+
+        let metrics = &METRICS.indexer_client;
+        assert_eq!(
+            metrics.status.with_label_values(&[&ok_label, "2xx"]).get(),
+            1
+        );
+        assert_eq!(
+            metrics
+                .errors
+                .with_label_values(&[&ok_label, "timeout"])
+                .get(),
+            0
+        );
+        assert_eq!(
+            metrics
+                .errors
+                .with_label_values(&[&stalled_label, "timeout"])
+                .get(),
+            1
+        );
+        assert_eq!(
+            metrics
+                .duration
+                .with_label_values(&[&stalled_label])
+                .get_sample_count(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn query_indexer_reuses_connections() {
+        //* Given
+        let peers: Arc<Mutex<HashSet<SocketAddr>>> = Default::default();
+        let router = Router::new()
+            .fallback(
+                |State(peers): State<Arc<Mutex<HashSet<SocketAddr>>>>,
+                 ConnectInfo(peer): ConnectInfo<SocketAddr>| async move {
+                    peers.lock().unwrap().insert(peer);
+                    r#"{"graphQLResponse":"{\"data\":{}}"}"#
+                },
+            )
+            .with_state(peers.clone());
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            axum::serve(
+                listener,
+                router.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await
+            .unwrap()
+        });
+        let url: Url = format!("http://{addr}/").parse().unwrap();
+
+        let client = IndexerClient::builder()
+            .with_pool_max_idle_per_host(1)
+            .with_pool_idle_timeout(Some(Duration::from_secs(30)))
+            .with_tcp_keepalive(Some(Duration::from_secs(30)))
+            .build();
+        let selection = test_selection(url);
+
+        //* When
+        for _ in 0..2 {
+            let result = client.query_indexer(&selection, "{}".to_string()).await;
+            assert_matches!(result, Ok(_));
+        }
+
+        //* Then
+        assert_eq!(peers.lock().unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn query_indexer_require_attestation() {
+        //* Given
+        let url =
+            serve(Router::new().fallback(|| async { r#"{"graphQLResponse":"{\"data\":{}}"}"# }))
+                .await;
+        let selection = test_selection(url);
+
+        let required = IndexerClient::builder()
+            .with_require_attestation(true)
+            .build();
+        let not_required = IndexerClient::builder()
+            .with_require_attestation(false)
+            .build();
+
+        //* When
+        let required_result = required.query_indexer(&selection, "{}".to_string()).await;
+        let not_required_result = not_required
+            .query_indexer(&selection, "{}".to_string())
+            .await;
+
+        //* Then
+        assert_matches!(required_result, Err(IndexerError::NoAttestation));
+        assert_matches!(not_required_result, Ok(response) => {
+            assert!(response.payload.attestation.is_none());
+        });
+    }
+
+    #[tokio::test]
+    async fn query_indexer_timing_breakdown() {
+        //* Given
+        let url = serve(Router::new().fallback(|| async {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            r#"{"graphQLResponse":"{\"data\":{}}"}"#
+        }))
+        .await;
+        let client = IndexerClient::builder().build();
+
+        //* When
+        let result = client
+            .query_indexer(&test_selection(url), "{}".to_string())
+            .await;
+
+        //* Then
+        assert_matches!(result, Ok(response) => {
+            let timing = response.timing;
+            assert!(timing.ttfb_ms >= 50);
+            // Each part is truncated to whole milliseconds, so their sum may be 1 ms short
+            assert!(timing.ttfb_ms + timing.body_ms <= timing.total_ms);
+            assert!(timing.total_ms <= timing.ttfb_ms + timing.body_ms + 1);
+        });
+    }
+
+    #[tokio::test]
+    async fn query_indexer_unexpected_payload() {
+        //* Given
+        let url = serve(Router::new().fallback(|| async { "<html>Not JSON</html>" })).await;
+        let client = IndexerClient::builder().build();
+
+        //* When
+        let result = client
+            .query_indexer(&test_selection(url), "{}".to_string())
+            .await;
+
+        //* Then
+        assert_matches!(result, Err(IndexerError::UnexpectedPayload(_)));
+    }
+
+    #[tokio::test]
+    async fn query_indexer_missing_graphql_response() {
+        //* Given
+        let url = serve(Router::new().fallback(|| async { r#"{"attestation":null}"# })).await;
+        let client = IndexerClient::builder().build();
+
+        //* When
+        let result = client
+            .query_indexer(&test_selection(url), "{}".to_string())
+            .await;
+
+        //* Then
+        assert_matches!(result, Err(IndexerError::UnexpectedPayload(message)) => {
+            assert_eq!(message, "missing GraphQL response");
+        });
+    }
+
+    #[tokio::test]
+    async fn query_path_joins_indexer_url() {
+        //* Given
+        let paths: Arc<Mutex<Vec<String>>> = Default::default();
+        let url = serve(
+            Router::new()
+                .fallback(
+                    |State(paths): State<Arc<Mutex<Vec<String>>>>,
+                     OriginalUri(uri): OriginalUri| async move {
+                        paths.lock().unwrap().push(uri.path().to_string());
+                        r#"{"graphQLResponse":"{\"data\":{}}"}"#
+                    },
+                )
+                .with_state(paths.clone()),
+        )
+        .await;
+        let client = IndexerClient::builder().build();
+        let selection = test_selection(url);
+
+        //* When
+        let status = client
+            .query_path(&selection, "status", "{}".to_string())
+            .await;
+        let query = client.query_indexer(&selection, "{}".to_string()).await;
+
+        //* Then
+        assert_matches!(status, Ok(_));
+        assert_matches!(query, Ok(_));
+        assert_eq!(
+            *paths.lock().unwrap(),
+            vec![
+                "/status".to_string(),
+                "/subgraphs/id/QmQqLJVgZLcRduoszARzRi12qGheUTWAHFf3ixMeGm2xML".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn base_path_is_normalized() {
+        assert_eq!(normalize_base_path(""), "");
+        assert_eq!(normalize_base_path("/"), "");
+        assert_eq!(normalize_base_path("proxy"), "proxy/");
+        assert_eq!(normalize_base_path("/proxy/"), "proxy/");
+        assert_eq!(normalize_base_path("//proxy//graph/"), "proxy/graph/");
+    }
+
+    #[tokio::test]
+    async fn query_indexer_honors_base_path() {
+        //* Given
+        let paths: Arc<Mutex<Vec<String>>> = Default::default();
+        let url = serve(
+            Router::new()
+                .fallback(
+                    |State(paths): State<Arc<Mutex<Vec<String>>>>,
+                     OriginalUri(uri): OriginalUri| async move {
+                        paths.lock().unwrap().push(uri.path().to_string());
+                        r#"{"graphQLResponse":"{\"data\":{}}"}"#
+                    },
+                )
+                .with_state(paths.clone()),
+        )
+        .await;
+        let client = IndexerClient::builder().with_base_path("/proxy/").build();
+
+        //* When
+        let result = client
+            .query_indexer(&test_selection(url), "{}".to_string())
+            .await;
+
+        //* Then
+        assert_matches!(result, Ok(_));
+        assert_eq!(
+            *paths.lock().unwrap(),
+            vec!["/proxy/subgraphs/id/QmQqLJVgZLcRduoszARzRi12qGheUTWAHFf3ixMeGm2xML".to_string()]
+        );
+    }
+
+    /// Build a receipt signer with an allocation for the given selection's indexing, and attach a
+    /// fresh receipt to the selection.
+    async fn attach_test_receipt(selection: &mut Selection) -> ReceiptSigner {
+        let legacy_signer: &'static SecretKey =
+            Box::leak(Box::new(SecretKey::from_slice(&[0x22; 32]).unwrap()));
+        let receipt_signer = ReceiptSigner::new(
+            SecretKey::from_slice(&[0x11; 32]).unwrap(),
+            U256::from(1),
+            Address::repeat_byte(0x01),
+            legacy_signer,
+        )
+        .await;
+        receipt_signer
+            .update_allocations(HashMap::from([(
+                selection.indexing,
+                Address::repeat_byte(0x02),
+            )]))
+            .await;
+        selection.receipt = receipt_signer
+            .create_receipt(&selection.indexing, 1_000)
+            .await
+            .unwrap();
+        receipt_signer
+    }
+
+    #[tokio::test]
+    async fn query_indexer_with_retries_renews_possibly_accepted_receipt() {
+        //* Given
+        let receipts: Arc<Mutex<Vec<String>>> = Default::default();
+        let url = serve(
+            Router::new()
+                .fallback(
+                    |State(receipts): State<Arc<Mutex<Vec<String>>>>,
+                     headers: HeaderMap| async move {
+                        let attempt = {
+                            let mut receipts = receipts.lock().unwrap();
+                            receipts.push(headers["Scalar-Receipt"].to_str().unwrap().to_string());
+                            receipts.len()
+                        };
+                        match attempt {
+                            // Time out before the receipt is accepted
+                            1 => {
+                                tokio::time::sleep(Duration::from_secs(1)).await;
+                                (StatusCode::OK, r#"{"graphQLResponse":"{\"data\":{}}"}"#)
+                            }
+                            // Accept the receipt, but fail with a server error
+                            2 => (StatusCode::INTERNAL_SERVER_ERROR, ""),
+                            _ => (StatusCode::OK, r#"{"graphQLResponse":"{\"data\":{}}"}"#),
+                        }
+                    },
+                )
+                .with_state(receipts.clone()),
+        )
+        .await;
+
+        let client = IndexerClient {
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_millis(100))
+                .build()
+                .unwrap(),
+            require_attestation: false,
+            base_path: String::new(),
+        };
+        let mut selection = test_selection(url);
+        let receipt_signer = attach_test_receipt(&mut selection).await;
+        let original_receipt = selection.receipt.serialize();
+
+        //* When
+        let (result, renewed_fees) = client
+            .query_indexer_with_retries(&receipt_signer, &mut selection, "{}".to_string(), 2)
+            .await;
+
+        //* Then
+        assert_matches!(result, Ok(_));
+        let receipts = receipts.lock().unwrap();
+        assert_eq!(receipts.len(), 3);
+        assert_eq!(receipts[0], original_receipt);
+        // The original receipt is reused after the timeout
+        assert_eq!(receipts[1], original_receipt);
+        // The receipt is renewed after the server error
+        assert_ne!(receipts[2], receipts[1]);
+        assert_eq!(selection.receipt.serialize(), receipts[2]);
+        assert_eq!(renewed_fees, selection.receipt.grt_value());
+    }
+
+    #[tokio::test]
+    async fn query_indexer_with_retries_skips_non_transient_errors() {
+        //* Given
+        let queries = Arc::new(AtomicUsize::new(0));
+        let url = serve(
+            Router::new()
+                .fallback(|State(queries): State<Arc<AtomicUsize>>| async move {
+                    queries.fetch_add(1, Ordering::SeqCst);
+                    "not json"
+                })
+                .with_state(queries.clone()),
+        )
+        .await;
+        let client = IndexerClient::builder().build();
+        let mut selection = test_selection(url);
+        let receipt_signer = attach_test_receipt(&mut selection).await;
+        let original_receipt = selection.receipt.serialize();
+
+        //* When
+        let (result, renewed_fees) = client
+            .query_indexer_with_retries(&receipt_signer, &mut selection, "{}".to_string(), 2)
+            .await;
+
+        //* Then
+        assert_matches!(result, Err(IndexerError::UnexpectedPayload(_)));
+        assert_eq!(queries.load(Ordering::SeqCst), 1);
+        assert_eq!(renewed_fees, 0);
+        assert_eq!(selection.receipt.serialize(), original_receipt);
+    }
+
+    #[test]
+    fn only_transient_errors_are_retried() {
+        assert!(super::is_transient_error(&IndexerError::Timeout));
+        assert!(super::is_transient_error(&IndexerError::Unavailable(
+            UnavailableReason::Unreachable
+        )));
+        assert!(super::is_transient_error(&IndexerError::Unavailable(
+            UnavailableReason::ServerError
+        )));
+        assert!(!super::is_transient_error(&IndexerError::Unavailable(
+            UnavailableReason::NoAllocation
+        )));
+        assert!(!super::is_transient_error(&IndexerError::BadResponse(
+            "400 Bad Request".to_string()
+        )));
+        assert!(!super::is_transient_error(
+            &IndexerError::UnexpectedPayload("not json".to_string())
+        ));
+    }
+
+    #[test]
+    fn receipt_is_reused_after_connect_errors_and_timeouts() {
+        assert!(super::is_receipt_unaccepted_error(
+            &IndexerError::Unavailable(UnavailableReason::Unreachable)
+        ));
+        assert!(super::is_receipt_unaccepted_error(&IndexerError::Timeout));
+        assert!(!super::is_receipt_unaccepted_error(
+            &IndexerError::Unavailable(UnavailableReason::ServerError)
+        ));
+        assert!(!super::is_receipt_unaccepted_error(
+            &IndexerError::BadResponse("failed to connect".to_string())
+        ));
+        assert!(!super::is_receipt_unaccepted_error(
+            &IndexerError::Unavailable(UnavailableReason::NoStatus)
+        ));
+    }
 }
@@ -1,4 +1,4 @@
-use alloy_primitives::BlockNumber;
+use alloy_primitives::{BlockHash, BlockNumber};
 use anyhow::{bail, ensure};
 use futures::future::join_all;
 use indoc::formatdoc;
@@ -21,7 +21,7 @@ pub async fn query(
                     subgraph
                     chains {{
                         network
-                        latestBlock {{ number }}
+                        latestBlock {{ number hash }}
                         earliestBlock {{ number }}
                     }}
                 }}
@@ -75,6 +75,10 @@ pub struct ChainStatus {
 pub struct BlockStatus {
     #[serde_as(as = "serde_with::DisplayFromStr")]
     pub number: BlockNumber,
+    /// The block hash, if reported and valid.
+    #[serde_as(as = "serde_with::DefaultOnError")]
+    #[serde(default)]
+    pub hash: Option<BlockHash>,
 }
 
 #[cfg(test)]
@@ -135,6 +139,24 @@ mod tests {
             assert!(response.indexing_statuses[0].chains[0]
                 .earliest_block
                 .is_some());
+            assert_eq!(
+                response.indexing_statuses[0].chains[0]
+                    .latest_block
+                    .as_ref()
+                    .and_then(|block| block.hash),
+                Some(
+                    "0xaa94881130ba16c28cc90a5a880b117bdc90b6b11e9cde0c78804cdb93cc9e85"
+                        .parse()
+                        .unwrap()
+                )
+            );
+            assert_eq!(
+                response.indexing_statuses[0].chains[0]
+                    .earliest_block
+                    .as_ref()
+                    .and_then(|block| block.hash),
+                None
+            );
 
             assert_eq!(response.indexing_statuses[1].chains.len(), 1);
             assert_eq!(response.indexing_statuses[1].chains[0].network, "rinkeby");
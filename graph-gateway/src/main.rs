@@ -21,11 +21,11 @@ use axum::{
     response::Response,
     routing, Router,
 };
-use config::{ApiKeys, Config, ExchangeRateProvider, Subscriptions};
+use config::{ApiKeyRateLimit, ApiKeys, Config, ExchangeRateProvider, Subscriptions};
 use eventuals::{Eventual, EventualExt as _, Ptr};
 use gateway_common::types::Indexing;
 use gateway_framework::{
-    auth::AuthContext,
+    auth::{methods::api_keys::rate_limit::ApiKeyTokenBucket, AuthContext},
     budgets::{Budgeter, USD},
     chains::Chains,
     http::middleware::{
@@ -35,15 +35,20 @@ use gateway_framework::{
     ip_blocker::IpBlocker,
     json,
     network::{
-        discovery::Status, exchange_rate, indexing_performance::IndexingPerformance,
+        discovery::Status,
+        exchange_rate::{self, GrtPerUsd},
+        indexing_performance::IndexingPerformance,
         network_subgraph,
     },
     reporting::{
         self, EventHandlerFn, KafkaClient, LoggingOptions, CLIENT_REQUEST_TARGET,
         INDEXER_REQUEST_TARGET,
     },
-    scalar::{self, ReceiptSigner},
-    subscriptions::subgraph as subscriptions_subgraph,
+    scalar::{self, ReceiptSigner, ReceiptSignerConfig},
+    subscriptions::{
+        subgraph as subscriptions_subgraph, DEFAULT_MAX_SIGNERS as SUBSCRIPTIONS_MAX_SIGNERS,
+        DEFAULT_MAX_STALENESS as SUBSCRIPTIONS_MAX_STALENESS,
+    },
     topology::network::{Deployment, GraphNetwork},
 };
 use graph_gateway::{
@@ -126,9 +131,10 @@ async fn main() {
         .build()
         .unwrap();
 
-    let grt_per_usd: watch::Receiver<NotNan<f64>> = match config.exchange_rate_provider {
+    let grt_per_usd: watch::Receiver<GrtPerUsd> = match config.exchange_rate_provider {
         ExchangeRateProvider::Fixed(grt_per_usd) => {
-            watch::channel(NotNan::new(grt_per_usd).expect("NAN exchange rate")).1
+            let rate = NotNan::new(grt_per_usd).expect("NAN exchange rate");
+            watch::channel(GrtPerUsd::fixed(rate)).1
         }
         ExchangeRateProvider::Rpc(url) => exchange_rate::grt_per_usd(url).await.unwrap(),
     };
@@ -174,6 +180,8 @@ async fn main() {
 
     let bad_indexers: &'static HashSet<Address> =
         Box::leak(Box::new(config.bad_indexers.into_iter().collect()));
+    let blocked_deployments: &'static HashSet<DeploymentId> =
+        Box::leak(Box::new(config.blocked_deployments.into_iter().collect()));
 
     let indexing_statuses = indexing::statuses(
         network.deployments.clone(),
@@ -191,13 +199,14 @@ async fn main() {
             .unwrap_or(config.scalar.signer.0),
     ));
     let receipt_signer: &'static ReceiptSigner = Box::leak(Box::new(
-        ReceiptSigner::new(
-            config.scalar.signer.0,
-            config.scalar.chain_id,
-            config.scalar.verifier,
+        ReceiptSigner::from_config(ReceiptSignerConfig {
+            signer: config.scalar.signer.0,
+            chain_id: config.scalar.chain_id,
+            verifier: config.scalar.verifier,
             legacy_signer,
-        )
-        .await,
+        })
+        .await
+        .expect("invalid scalar config"),
     ));
 
     eventuals::join((network.deployments.clone(), indexing_statuses.clone()))
@@ -210,6 +219,7 @@ async fn main() {
         config.payment_required,
         http_client.clone(),
         config.api_keys,
+        config.api_key_rate_limit,
         http_client.clone(),
         config.subscriptions,
     )
@@ -220,16 +230,31 @@ async fn main() {
     let budgeter: &'static Budgeter = Box::leak(Box::new(Budgeter::new(query_fees_target)));
 
     let client_query_ctx = Context {
-        indexer_client: IndexerClient {
-            client: http_client.clone(),
-        },
+        indexer_client: IndexerClient::builder()
+            .with_tcp_keepalive(Some(Duration::from_secs(60)))
+            .with_base_path(&config.indexer_base_path)
+            .build(),
         receipt_signer,
+        indexer_query_retries: config.indexer_query_retries,
         kafka_client,
         budgeter,
+        fee_rounding: config.fee_rounding,
         l2_gateway: config.l2_gateway,
+        max_query_bytes: config.max_query_bytes,
         chains: Box::leak(Box::new(Chains::new(config.chain_aliases))),
+        chain_block_times: Box::leak(Box::new(
+            config
+                .chain_block_times_ms
+                .into_iter()
+                .map(|(chain, block_time_ms)| (chain, Duration::from_millis(block_time_ms)))
+                .collect(),
+        )),
         grt_per_usd,
+        grt_per_usd_max_age: config
+            .exchange_rate_max_age
+            .map_or(exchange_rate::DEFAULT_MAX_AGE, Duration::from_secs),
         network,
+        blocked_deployments,
         indexing_perf: IndexingPerformance::new(indexing_statuses.clone()),
         indexing_statuses,
         attestation_domain,
@@ -444,6 +469,7 @@ async fn init_auth_service(
     payment_required: bool,
     api_keys_http_client: reqwest::Client,
     api_keys: Option<ApiKeys>,
+    api_key_rate_limit: Option<ApiKeyRateLimit>,
     subscriptions_http_client: reqwest::Client,
     subscriptions: Option<Subscriptions>,
 ) -> AuthContext {
@@ -477,6 +503,9 @@ async fn init_auth_service(
                 .with_auth_token(subscriptions.ticket.clone())
                 .build(),
                 subscriptions.allow_empty,
+                subscriptions
+                    .max_signers
+                    .unwrap_or(SUBSCRIPTIONS_MAX_SIGNERS),
             )
             .await
         }
@@ -486,7 +515,12 @@ async fn init_auth_service(
         payment_required,
         api_keys_ev,
         special_api_keys,
+        api_key_rate_limit.map(|limit| ApiKeyTokenBucket::new(limit.rate, limit.burst)),
         subscriptions_ev,
+        subscriptions
+            .as_ref()
+            .and_then(|s| s.max_staleness)
+            .map_or(SUBSCRIPTIONS_MAX_STALENESS, Duration::from_secs),
         subscriptions
             .iter()
             .flat_map(|s| s.special_signers.clone())
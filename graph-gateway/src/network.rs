@@ -7,10 +7,11 @@ pub use service::{
 };
 pub use snapshot::{
     BlockNumber, DeploymentId, Indexer, Indexing, IndexingId, IndexingStatus,
-    NetworkTopologySnapshot, SubgraphId,
+    NetworkTopologySnapshot, SnapshotStats, SubgraphId,
 };
 
 pub mod indexer_addr_blocklist;
+pub mod indexer_health_prober;
 pub mod indexer_host_blocklist;
 pub mod indexer_host_resolver;
 pub mod indexer_indexing_cost_model_compiler;
@@ -0,0 +1,152 @@
+//! Indexer URL health prober.
+//!
+//! The prober checks whether an indexer's URL is reachable by sending a `HEAD` request to it. The
+//! probe results are cached with a TTL, so the indexers are not probed on every network topology
+//! update.
+
+use std::time::Duration;
+
+use gateway_common::ttl_hash_map::TtlHashMap;
+use tokio::sync::RwLock;
+use url::Url;
+
+/// The default indexer health probe timeout.
+pub const DEFAULT_INDEXER_HEALTH_PROBE_TIMEOUT: Duration = Duration::from_millis(1_500);
+
+/// The default TTL of the cached indexer health probe results.
+pub const DEFAULT_INDEXER_HEALTH_PROBE_CACHE_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// The indexer URL health prober.
+///
+/// An indexer URL is considered reachable if it responds to the probe within the timeout with a
+/// non-server-error status code.
+pub struct HealthProber {
+    /// The indexer client.
+    client: reqwest::Client,
+    /// The health probe timeout.
+    timeout: Duration,
+    /// The cache of the health probe results.
+    cache: RwLock<TtlHashMap<Url, bool>>,
+}
+
+impl HealthProber {
+    /// Creates a new [`HealthProber`] instance with the provided client.
+    ///
+    /// The prober will use the default timeout and cache TTL,
+    /// [`DEFAULT_INDEXER_HEALTH_PROBE_TIMEOUT`] and [`DEFAULT_INDEXER_HEALTH_PROBE_CACHE_TTL`].
+    pub fn new(client: reqwest::Client) -> Self {
+        Self::with_timeout_and_cache_ttl(
+            client,
+            DEFAULT_INDEXER_HEALTH_PROBE_TIMEOUT,
+            DEFAULT_INDEXER_HEALTH_PROBE_CACHE_TTL,
+        )
+    }
+
+    /// Creates a new [`HealthProber`] instance with the provided client, timeout and cache TTL.
+    pub fn with_timeout_and_cache_ttl(
+        client: reqwest::Client,
+        timeout: Duration,
+        cache_ttl: Duration,
+    ) -> Self {
+        Self {
+            client,
+            timeout,
+            cache: RwLock::new(TtlHashMap::with_ttl(cache_ttl)),
+        }
+    }
+
+    /// Probes the indexer URL, returning whether it is reachable.
+    ///
+    /// If a non-expired probe result is cached for the URL, it is returned without probing.
+    pub async fn probe(&self, url: &Url) -> bool {
+        if let Some(reachable) = self.cache.read().await.peek(url) {
+            return *reachable;
+        }
+
+        // The cache lock is not held while probing, so the indexers can be probed concurrently
+        let reachable =
+            match tokio::time::timeout(self.timeout, self.client.head(url.clone()).send()).await {
+                Ok(Ok(response)) => !response.status().is_server_error(),
+                Ok(Err(_)) | Err(_) => false,
+            };
+
+        self.cache.write().await.insert(url.clone(), reachable);
+        reachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        time::Duration,
+    };
+
+    use axum::{routing::get, Router};
+    use gateway_framework::test_utils::serve;
+    use tokio::net::TcpListener;
+    use url::Url;
+
+    use super::HealthProber;
+
+    /// Returns the URL of a local port with no server listening on it.
+    async fn unreachable_url() -> Url {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener);
+        format!("http://{addr}/").parse().unwrap()
+    }
+
+    #[tokio::test]
+    async fn probe_reachable_and_unreachable_indexers() {
+        //* Given
+        let reachable_url =
+            serve(Router::new().route("/", get(|| async { "Ready to roll!" }))).await;
+        let unreachable_url = unreachable_url().await;
+
+        let prober = HealthProber::new(reqwest::Client::new());
+
+        //* When
+        let reachable = prober.probe(&reachable_url).await;
+        let unreachable = prober.probe(&unreachable_url).await;
+
+        //* Then
+        assert!(reachable);
+        assert!(!unreachable);
+    }
+
+    #[tokio::test]
+    async fn probe_results_are_cached() {
+        //* Given
+        let probes = Arc::new(AtomicUsize::new(0));
+        let url = serve(Router::new().route(
+            "/",
+            get({
+                let probes = probes.clone();
+                move || async move {
+                    probes.fetch_add(1, Ordering::SeqCst);
+                    ""
+                }
+            }),
+        ))
+        .await;
+
+        let prober = HealthProber::with_timeout_and_cache_ttl(
+            reqwest::Client::new(),
+            Duration::from_secs(1),
+            Duration::from_secs(60),
+        );
+
+        //* When
+        let first = prober.probe(&url).await;
+        let second = prober.probe(&url).await;
+
+        //* Then
+        assert!(first);
+        assert!(second);
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+    }
+}
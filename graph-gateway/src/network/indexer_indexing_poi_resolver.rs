@@ -6,7 +6,10 @@
 //! The cache has a TTL of 20 minutes. Entries are considered expired after this time causing the
 //! resolver to fetch the public POIs of the indexer again.
 
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    time::Duration,
+};
 
 use alloy_primitives::BlockNumber;
 use gateway_common::ttl_hash_map::TtlHashMap;
@@ -32,6 +35,17 @@ pub enum ResolutionError {
     Timeout,
 }
 
+/// The result of checking the public POIs reported by an indexer against the expected POIs.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct PoiCheckResult {
+    /// The POIs metadata whose reported POI matches the expected POI.
+    pub matched: HashSet<(DeploymentId, BlockNumber)>,
+    /// The POIs metadata whose reported POI differs from the expected POI.
+    pub mismatched: HashSet<(DeploymentId, BlockNumber)>,
+    /// The POIs metadata the indexer did not report a POI for.
+    pub missing: HashSet<(DeploymentId, BlockNumber)>,
+}
+
 /// A resolver for the Proof of Indexing (POI) of indexers.
 pub struct PoiResolver {
     client: reqwest::Client,
@@ -110,4 +124,69 @@ impl PoiResolver {
             }
         }
     }
+
+    /// Resolve the public POIs of the indexer and check them against the expected POIs.
+    ///
+    /// The indexer is queried for the POIs metadata of the expected POIs. Each expected POI is
+    /// classified as matched, mismatched or missing (i.e., not reported by the indexer).
+    pub async fn resolve_and_check(
+        &mut self,
+        url: &Url,
+        expected: &HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>,
+    ) -> Result<PoiCheckResult, ResolutionError> {
+        let pois = expected.keys().copied().collect::<Vec<_>>();
+        let reported = self.resolve(url, &pois).await?;
+        Ok(check_pois(expected, &reported))
+    }
+}
+
+/// Classify the expected POIs based on the POIs reported by the indexer.
+fn check_pois(
+    expected: &HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>,
+    reported: &HashMap<(DeploymentId, BlockNumber), ProofOfIndexing>,
+) -> PoiCheckResult {
+    let mut result = PoiCheckResult::default();
+    for (meta, expected_poi) in expected {
+        match reported.get(meta) {
+            Some(poi) if poi == expected_poi => result.matched.insert(*meta),
+            Some(_) => result.mismatched.insert(*meta),
+            None => result.missing.insert(*meta),
+        };
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{HashMap, HashSet};
+
+    use thegraph_core::types::{DeploymentId, ProofOfIndexing};
+
+    use super::check_pois;
+
+    #[test]
+    fn classify_matching_mismatching_and_missing_pois() {
+        //* Given
+        let deployment: DeploymentId = "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+            .parse()
+            .expect("invalid deployment ID");
+
+        let expected = HashMap::from([
+            ((deployment, 100), ProofOfIndexing::repeat_byte(0x01)),
+            ((deployment, 200), ProofOfIndexing::repeat_byte(0x02)),
+            ((deployment, 300), ProofOfIndexing::repeat_byte(0x03)),
+        ]);
+        let reported = HashMap::from([
+            ((deployment, 100), ProofOfIndexing::repeat_byte(0x01)),
+            ((deployment, 200), ProofOfIndexing::repeat_byte(0xff)),
+        ]);
+
+        //* When
+        let result = check_pois(&expected, &reported);
+
+        //* Then
+        assert_eq!(result.matched, HashSet::from([(deployment, 100)]));
+        assert_eq!(result.mismatched, HashSet::from([(deployment, 200)]));
+        assert_eq!(result.missing, HashSet::from([(deployment, 300)]));
+    }
 }
@@ -2,7 +2,7 @@
 
 use std::{collections::HashMap, time::Duration};
 
-use alloy_primitives::BlockNumber;
+use alloy_primitives::{BlockHash, BlockNumber};
 use thegraph_core::types::DeploymentId;
 use url::Url;
 
@@ -33,6 +33,8 @@ pub struct IndexingProgressInfo {
     pub chain: String,
     /// The latest block number indexed by the indexer.
     pub latest_block: BlockNumber,
+    /// The latest block hash indexed by the indexer, if reported.
+    pub latest_block_hash: Option<BlockHash>,
     /// The earliest block number indexed by the indexer.
     pub min_block: Option<BlockNumber>,
 }
@@ -96,7 +98,8 @@ impl IndexingProgressResolver {
 
                 // If the status has no chains or no latest block, skip it
                 let status_chain = chain.network;
-                let status_latest_block = chain.latest_block.map(|block| block.number)?;
+                let status_latest_block = chain.latest_block.as_ref().map(|block| block.number)?;
+                let status_latest_block_hash = chain.latest_block.and_then(|block| block.hash);
                 let status_min_block = chain.earliest_block.as_ref().map(|block| block.number);
 
                 Some((
@@ -104,6 +107,7 @@ impl IndexingProgressResolver {
                     IndexingProgressInfo {
                         chain: status_chain,
                         latest_block: status_latest_block,
+                        latest_block_hash: status_latest_block_hash,
                         min_block: status_min_block,
                     },
                 ))
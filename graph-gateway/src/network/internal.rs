@@ -1,11 +1,14 @@
-use std::{collections::HashMap, time::Duration};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    time::Duration,
+};
 
-use alloy_primitives::Address;
+use alloy_primitives::{Address, BlockNumber};
 use anyhow::anyhow;
 use gateway_common::blocklist::Blocklist as _;
 use itertools::Itertools;
 use semver::Version;
-use thegraph_core::types::SubgraphId;
+use thegraph_core::types::{DeploymentId, SubgraphId};
 use tokio::sync::Mutex;
 use tracing::Instrument;
 use url::Url;
@@ -16,8 +19,9 @@ use self::types::{
     SubgraphVersionInfo,
 };
 use super::{
-    indexer_addr_blocklist::AddrBlocklist, indexer_host_blocklist::HostBlocklist,
-    indexer_host_resolver::HostResolver, indexer_indexing_cost_model_compiler::CostModelCompiler,
+    indexer_addr_blocklist::AddrBlocklist, indexer_health_prober::HealthProber,
+    indexer_host_blocklist::HostBlocklist, indexer_host_resolver::HostResolver,
+    indexer_indexing_cost_model_compiler::CostModelCompiler,
     indexer_indexing_cost_model_resolver::CostModelResolver,
     indexer_indexing_poi_blocklist::PoiBlocklist, indexer_indexing_poi_resolver::PoiResolver,
     indexer_indexing_progress_resolver::IndexingProgressResolver,
@@ -34,7 +38,7 @@ const NETWORK_TOPOLOGY_FETCH_TIMEOUT: Duration = Duration::from_secs(15);
 pub mod types {
     use std::{collections::HashMap, fmt::Display};
 
-    use alloy_primitives::{Address, BlockNumber};
+    use alloy_primitives::{Address, BlockHash, BlockNumber};
     use cost_model::CostModel;
     use custom_debug::CustomDebug;
     use eventuals::Ptr;
@@ -50,6 +54,8 @@ pub mod types {
     pub struct SubgraphInfo {
         pub id: SubgraphId,
         pub id_on_l2: Option<SubgraphId>,
+        /// The subgraph's current version number, if any.
+        pub current_version: Option<u32>,
         pub versions: Vec1<SubgraphVersionInfo>,
     }
 
@@ -68,6 +74,7 @@ pub mod types {
         pub allocations: Vec<AllocationInfo>,
         pub manifest_network: Option<String>,
         pub manifest_start_block: Option<BlockNumber>,
+        pub manifest_features: Vec<String>,
         pub transferred_to_l2: bool,
     }
 
@@ -107,6 +114,11 @@ pub mod types {
         /// The indexer's "graph node" version.
         pub graph_node_version: Version,
 
+        /// Whether the indexer's URL is reachable.
+        ///
+        /// Indexers are assumed to be reachable unless probed otherwise.
+        pub reachable: bool,
+
         /// The largest allocation per indexing.
         pub largest_allocation: HashMap<DeploymentId, Address>,
         /// The total amount of tokens allocated by the indexer per indexing.
@@ -123,11 +135,27 @@ pub mod types {
     pub struct IndexerIndexingProgressInfo {
         /// The latest block the indexer has indexed for the deployment.
         pub latest_block: BlockNumber,
+        /// The hash of the latest block the indexer has indexed for the deployment, if reported.
+        pub latest_block_hash: Option<BlockHash>,
         /// The minimum block the indexer has indexed for the deployment.
         pub min_block: Option<BlockNumber>,
     }
 }
 
+/// The policy applied to the indexings whose indexer did not report an indexing status.
+///
+/// The indexings without a status have unknown freshness, so the indexer selection can't rank them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum StatuslessIndexingPolicy {
+    /// Keep the indexings without a status.
+    #[default]
+    Include,
+    /// Exclude the indexings without a status.
+    Exclude,
+    /// Assign the indexings without a status a pessimistic status with the given latest block.
+    AssumeLatestBlock(BlockNumber),
+}
+
 /// Internal type holding the network service state.
 pub struct InternalState {
     pub indexer_http_client: reqwest::Client,
@@ -137,9 +165,13 @@ pub struct InternalState {
     pub indexer_host_resolver: Mutex<HostResolver>,
     pub indexer_host_blocklist: Option<HostBlocklist>,
     pub indexer_version_resolver: VersionResolver,
+    pub indexer_health_prober: Option<HealthProber>,
     pub indexer_indexing_pois_blocklist: Option<(PoiBlocklist, Mutex<PoiResolver>)>,
     pub indexer_indexing_status_resolver: IndexingProgressResolver,
+    pub indexer_indexing_statusless_policy: StatuslessIndexingPolicy,
     pub indexer_indexing_cost_model_resolver: (CostModelResolver, Mutex<CostModelCompiler>),
+    pub deployment_blocklist: HashSet<DeploymentId>,
+    pub chain_aliases: BTreeMap<String, String>,
 }
 
 /// Fetch the network topology information from the graph network subgraph.
@@ -191,7 +223,12 @@ pub async fn fetch_update(
     )
     .await?;
 
-    Ok(snapshot::new_from(indexers_info, subgraphs_info))
+    Ok(snapshot::new_from(
+        indexers_info,
+        subgraphs_info,
+        &state.deployment_blocklist,
+        &state.chain_aliases,
+    ))
 }
 
 /// Fetch the indexers information from the graph network subgraph and performs pre-processing
@@ -380,6 +417,7 @@ fn try_into_internal_indexer_info(
         total_allocated_tokens: indexer_indexing_total_allocated_tokens,
         indexer_agent_version: Version::new(0, 0, 0), // Placeholder
         graph_node_version: Version::new(0, 0, 0),    // Placeholder
+        reachable: true,                              // Placeholder
         indexings_progress: HashMap::new(),           // Placeholder
         indexings_cost_model: HashMap::new(),         // Placeholder
     })
@@ -424,6 +462,8 @@ fn try_into_internal_subgraph_info(
                 allocations: deployment_allocations,
                 manifest_network: deployment_manifest_network,
                 manifest_start_block: deployment_manifest_start_block,
+                // TODO: The network subgraph does not expose the manifest's declared features
+                manifest_features: Vec::new(),
                 transferred_to_l2: deployment_transferred_to_l2,
             };
 
@@ -439,6 +479,7 @@ fn try_into_internal_subgraph_info(
     Ok(SubgraphInfo {
         id: subgraph.id,
         id_on_l2: subgraph.id_on_l2,
+        current_version: subgraph.current_version.map(|version| version.version),
         versions,
     })
 }
@@ -508,6 +549,15 @@ pub async fn process_indexers_info(
                         tracing::field::display(&indexer.graph_node_version),
                     );
 
+                // Probe the indexer's URL reachability, if the health prober is configured.
+                // Unreachable indexers are not filtered-out, but marked as such.
+                if let Some(prober) = &state.indexer_health_prober {
+                    indexer.reachable = prober.probe(&indexer.url).await;
+                    if !indexer.reachable {
+                        tracing::debug!("indexer URL unreachable");
+                    }
+                }
+
                 // Check if the indexer's deployments should be blocked by POI
                 // Update the indexer's deployments list to only include the deployments that are
                 // not blocked by POI. If the indexer has no deployments left, it must be ignored.
@@ -534,6 +584,15 @@ pub async fn process_indexers_info(
                     return None;
                 }
 
+                // Apply the policy to the indexings without an indexing progress status
+                if let Err(err) = apply_statusless_indexing_policy(
+                    &state.indexer_indexing_statusless_policy,
+                    &mut indexer,
+                ) {
+                    tracing::debug!("filtering-out indexer: {err}");
+                    return None;
+                }
+
                 // Fetch the indexer's indexing statuses and cost models
                 // NOTE: At this point, the indexer's deployments list should contain only the
                 //       deployment IDs that were not blocked by any blocklist.
@@ -753,6 +812,7 @@ async fn resolve_indexer_indexing_progress_statuses(
                 deployment_id,
                 IndexerIndexingProgressInfo {
                     latest_block: res.latest_block,
+                    latest_block_hash: res.latest_block_hash,
                     min_block: res.min_block,
                 },
             )
@@ -765,6 +825,44 @@ async fn resolve_indexer_indexing_progress_statuses(
     Ok(())
 }
 
+/// Apply the statusless indexing policy to the indexer's deployments without an indexing progress
+/// status.
+///
+/// - If the policy is to include them: the indexings are kept without a status.
+/// - If the policy is to exclude them: the deployments are removed from the indexer's list. If no
+///   deployments are left, the indexer must be BLOCKED.
+/// - If the policy is to assume a latest block: the indexings are assigned a status with that
+///   latest block.
+fn apply_statusless_indexing_policy(
+    policy: &StatuslessIndexingPolicy,
+    indexer: &mut IndexerInfo,
+) -> anyhow::Result<()> {
+    match policy {
+        StatuslessIndexingPolicy::Include => {}
+        StatuslessIndexingPolicy::Exclude => {
+            let indexings_progress = &indexer.indexings_progress;
+            indexer
+                .deployments
+                .retain(|id| indexings_progress.contains_key(id))
+                .map_err(|_| anyhow!("no deployments with an indexing status"))?;
+        }
+        StatuslessIndexingPolicy::AssumeLatestBlock(latest_block) => {
+            for deployment_id in indexer.deployments.iter() {
+                indexer
+                    .indexings_progress
+                    .entry(*deployment_id)
+                    .or_insert_with(|| IndexerIndexingProgressInfo {
+                        latest_block: *latest_block,
+                        latest_block_hash: None,
+                        min_block: None,
+                    });
+            }
+        }
+    }
+
+    Ok(())
+}
+
 /// Resolve the indexer's indexing cost models.
 async fn resolve_indexer_indexing_cost_models(
     (resolver, compiler): &(CostModelResolver, Mutex<CostModelCompiler>),
@@ -804,3 +902,88 @@ async fn resolve_indexer_indexing_cost_models(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use alloy_primitives::Address;
+    use semver::Version;
+    use thegraph_core::types::DeploymentId;
+    use vec1::vec1;
+
+    use super::{
+        apply_statusless_indexing_policy,
+        types::{IndexerIndexingProgressInfo, IndexerInfo},
+        StatuslessIndexingPolicy,
+    };
+
+    /// Test helper to build an [`IndexerInfo`] with a status for the first deployment only.
+    fn test_indexer_info(with_status: DeploymentId, statusless: DeploymentId) -> IndexerInfo {
+        IndexerInfo {
+            id: Address::repeat_byte(0x01),
+            url: "https://indexer.example.com/".parse().unwrap(),
+            staked_tokens: 1_000,
+            deployments: vec1![with_status, statusless],
+            indexer_agent_version: Version::new(1, 0, 0),
+            graph_node_version: Version::new(0, 35, 0),
+            reachable: true,
+            largest_allocation: HashMap::new(),
+            total_allocated_tokens: HashMap::new(),
+            indexings_progress: HashMap::from([(
+                with_status,
+                IndexerIndexingProgressInfo {
+                    latest_block: 100,
+                    latest_block_hash: None,
+                    min_block: None,
+                },
+            )]),
+            indexings_cost_model: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn exclude_statusless_indexings() {
+        //* Given
+        let with_status: DeploymentId = "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+            .parse()
+            .unwrap();
+        let statusless: DeploymentId = "QmSLQfPFcz2pKRJZUH16Sk26EFpRgdxTYGnMiKvWgKRM2a"
+            .parse()
+            .unwrap();
+        let mut indexer = test_indexer_info(with_status, statusless);
+
+        //* When
+        let result =
+            apply_statusless_indexing_policy(&StatuslessIndexingPolicy::Exclude, &mut indexer);
+
+        //* Then
+        assert!(result.is_ok());
+        assert_eq!(indexer.deployments, vec1![with_status]);
+        assert!(!indexer.indexings_progress.contains_key(&statusless));
+    }
+
+    #[test]
+    fn assume_latest_block_for_statusless_indexings() {
+        //* Given
+        let with_status: DeploymentId = "QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH"
+            .parse()
+            .unwrap();
+        let statusless: DeploymentId = "QmSLQfPFcz2pKRJZUH16Sk26EFpRgdxTYGnMiKvWgKRM2a"
+            .parse()
+            .unwrap();
+        let mut indexer = test_indexer_info(with_status, statusless);
+
+        //* When
+        let result = apply_statusless_indexing_policy(
+            &StatuslessIndexingPolicy::AssumeLatestBlock(0),
+            &mut indexer,
+        );
+
+        //* Then
+        assert!(result.is_ok());
+        assert_eq!(indexer.deployments, vec1![with_status, statusless]);
+        assert_eq!(indexer.indexings_progress[&with_status].latest_block, 100);
+        assert_eq!(indexer.indexings_progress[&statusless].latest_block, 0);
+    }
+}
@@ -3,7 +3,7 @@
 //! query processing pipeline
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     time::Duration,
 };
 
@@ -17,6 +17,7 @@ use vec1::{vec1, Vec1};
 
 use super::{
     indexer_addr_blocklist::AddrBlocklist,
+    indexer_health_prober::HealthProber,
     indexer_host_blocklist::HostBlocklist,
     indexer_host_resolver::HostResolver,
     indexer_indexing_cost_model_compiler::CostModelCompiler,
@@ -25,7 +26,7 @@ use super::{
     indexer_indexing_poi_resolver::PoiResolver,
     indexer_indexing_progress_resolver::IndexingProgressResolver,
     indexer_version_resolver::{VersionResolver, DEFAULT_INDEXER_VERSION_RESOLUTION_TIMEOUT},
-    internal::{fetch_update, InternalState},
+    internal::{fetch_update, InternalState, StatuslessIndexingPolicy},
     snapshot::{
         Address, BlockNumber, DeploymentId, Indexing, IndexingId, NetworkTopologySnapshot,
         SubgraphId,
@@ -35,6 +36,7 @@ use super::{
 use crate::{
     indexers::public_poi::ProofOfIndexingInfo,
     network::{
+        indexer_health_prober::DEFAULT_INDEXER_HEALTH_PROBE_TIMEOUT,
         indexer_host_resolver::DEFAULT_INDEXER_HOST_RESOLUTION_TIMEOUT,
         indexer_indexing_cost_model_resolver::DEFAULT_INDEXER_INDEXING_COST_MODEL_RESOLUTION_TIMEOUT,
         indexer_indexing_poi_resolver::DEFAULT_INDEXER_INDEXING_POIS_RESOLUTION_TIMEOUT,
@@ -116,7 +118,12 @@ impl NetworkService {
             let indexings = network
                 .subgraphs()
                 .values()
-                .flat_map(|subgraph| subgraph.indexings.clone())
+                .flat_map(|subgraph| {
+                    subgraph
+                        .indexings
+                        .iter()
+                        .map(|(id, indexing)| (*id, Indexing::clone(indexing)))
+                })
                 .collect();
 
             Ptr::new(indexings)
@@ -133,9 +140,9 @@ impl NetworkService {
             .ok_or(Error::Internal(anyhow!("network topology not available")))?;
 
         // Check if the subgraph is transferred to L2
-        if let Some(id_on_l2) = network.transferred_subgraphs().get(id) {
+        if let Some(id_on_l2) = network.resolve_l2_subgraph(id) {
             return Ok(SubgraphResolution::TransferredToL2 {
-                id_on_l2: Some(*id_on_l2),
+                id_on_l2: Some(id_on_l2),
             });
         }
 
@@ -157,7 +164,11 @@ impl NetworkService {
             .try_into()
             .map_err(|_| anyhow!("no deployments found for subgraph {id}"))?;
 
-        let indexings = subgraph.indexings.clone();
+        let indexings = subgraph
+            .indexings
+            .iter()
+            .map(|(id, indexing)| (*id, Indexing::clone(indexing)))
+            .collect();
 
         Ok(SubgraphResolution::Resolved(ResolvedSubgraphInfo {
             chain: subgraph_chain,
@@ -203,7 +214,11 @@ impl NetworkService {
             .map_err(|_| anyhow!("no subgraphs found for deployment {id}"))?;
         let deployments = vec1![deployment.id];
 
-        let indexings = deployment.indexings.clone();
+        let indexings = deployment
+            .indexings
+            .iter()
+            .map(|(id, indexing)| (*id, Indexing::clone(indexing)))
+            .collect();
 
         Ok(SubgraphResolution::Resolved(ResolvedSubgraphInfo {
             chain: deployment_chain,
@@ -225,10 +240,14 @@ pub struct NetworkServiceBuilder {
     indexer_host_resolver: HostResolver,
     indexer_host_blocklist: Option<HostBlocklist>,
     indexer_version_resolver: VersionResolver,
+    indexer_health_prober: Option<HealthProber>,
     indexer_indexing_pois_blocklist: Option<(PoiBlocklist, PoiResolver)>,
     indexer_indexing_status_resolver: IndexingProgressResolver,
+    indexer_indexing_statusless_policy: StatuslessIndexingPolicy,
     indexer_indexing_cost_model_resolver: CostModelResolver,
     indexer_indexing_cost_model_compiler: CostModelCompiler,
+    deployment_blocklist: HashSet<DeploymentId>,
+    chain_aliases: BTreeMap<String, String>,
     update_interval: Duration,
 }
 
@@ -262,10 +281,14 @@ impl NetworkServiceBuilder {
             indexer_host_resolver,
             indexer_host_blocklist: None,
             indexer_version_resolver,
+            indexer_health_prober: None,
             indexer_indexing_pois_blocklist: None,
             indexer_indexing_status_resolver,
+            indexer_indexing_statusless_policy: StatuslessIndexingPolicy::default(),
             indexer_indexing_cost_model_resolver,
             indexer_indexing_cost_model_compiler,
+            deployment_blocklist: HashSet::new(),
+            chain_aliases: BTreeMap::new(),
             update_interval: DEFAULT_UPDATE_INTERVAL,
         }
     }
@@ -316,6 +339,46 @@ impl NetworkServiceBuilder {
         self
     }
 
+    /// Sets the deployment blocklist.
+    ///
+    /// The blocked deployments and their indexings are excluded from the network topology.
+    pub fn with_deployment_blocklist(mut self, blocklist: HashSet<DeploymentId>) -> Self {
+        self.deployment_blocklist = blocklist;
+        self
+    }
+
+    /// Sets the chain aliases.
+    ///
+    /// The subgraphs and deployments manifest chain names found in the map are replaced by their
+    /// canonical name.
+    pub fn with_chain_aliases(mut self, aliases: BTreeMap<String, String>) -> Self {
+        self.chain_aliases = aliases;
+        self
+    }
+
+    /// Enables the indexer URL health probe.
+    ///
+    /// Indexers whose URL is not reachable are marked as such in the network topology. The probe
+    /// results are cached for the given TTL.
+    pub fn with_indexer_health_probe(mut self, cache_ttl: Duration) -> Self {
+        let prober = HealthProber::with_timeout_and_cache_ttl(
+            self.indexer_client.clone(),
+            DEFAULT_INDEXER_HEALTH_PROBE_TIMEOUT, // 1500ms
+            cache_ttl,
+        );
+
+        self.indexer_health_prober = Some(prober);
+        self
+    }
+
+    /// Sets the policy applied to the indexings whose indexer did not report an indexing status.
+    ///
+    /// By default, these indexings are included without a status.
+    pub fn with_statusless_indexing_policy(mut self, policy: StatuslessIndexingPolicy) -> Self {
+        self.indexer_indexing_statusless_policy = policy;
+        self
+    }
+
     /// Builds the [`NetworkService`] instance ready for spawning.
     ///
     /// To spawn the [`NetworkService`] instance, call the [`NetworkServicePending::spawn`] method.
@@ -328,14 +391,18 @@ impl NetworkServiceBuilder {
             indexer_host_resolver: Mutex::new(self.indexer_host_resolver),
             indexer_host_blocklist: self.indexer_host_blocklist,
             indexer_version_resolver: self.indexer_version_resolver,
+            indexer_health_prober: self.indexer_health_prober,
             indexer_indexing_pois_blocklist: self
                 .indexer_indexing_pois_blocklist
                 .map(|(bl, res)| (bl, Mutex::new(res))),
             indexer_indexing_status_resolver: self.indexer_indexing_status_resolver,
+            indexer_indexing_statusless_policy: self.indexer_indexing_statusless_policy,
             indexer_indexing_cost_model_resolver: (
                 self.indexer_indexing_cost_model_resolver,
                 Mutex::new(self.indexer_indexing_cost_model_compiler),
             ),
+            deployment_blocklist: self.deployment_blocklist,
+            chain_aliases: self.chain_aliases,
         };
 
         NetworkServicePending {
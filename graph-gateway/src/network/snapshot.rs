@@ -1,22 +1,30 @@
 //! Entities that are used to represent the network topology.
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     fmt::Display,
     ops::Deref,
     sync::{Arc, OnceLock},
 };
 
-pub use alloy_primitives::{Address, BlockNumber};
-use cost_model::CostModel;
+pub use alloy_primitives::{Address, BlockHash, BlockNumber};
+use cost_model::{Context as AgoraContext, CostModel};
 use custom_debug::CustomDebug;
 use eventuals::Ptr;
+use gateway_framework::scalar::ReceiptVersion;
+use num_traits::cast::ToPrimitive as _;
 use semver::Version;
 pub use thegraph_core::types::{DeploymentId, SubgraphId};
 use url::Url;
 
 use super::internal::types::{DeploymentInfo, IndexerInfo, SubgraphInfo};
 
+/// The tracing target of the events emitted when an entity is filtered-out during the snapshot
+/// construction.
+///
+/// These events can be enabled selectively, e.g., `RUST_LOG=network_snapshot_filter=debug`.
+pub const SNAPSHOT_FILTER_TARGET: &str = "network_snapshot_filter";
+
 /// The minimum indexer agent version required to support Scalar TAP.
 fn min_required_indexer_agent_version_scalar_tap_support() -> &'static Version {
     static VERSION: OnceLock<Version> = OnceLock::new();
@@ -39,6 +47,12 @@ pub struct Indexing {
 
     /// The versions behind the highest version of the subgraph being indexed.
     pub versions_behind: u8,
+    /// The signed difference between the indexed deployment's version and the current version of
+    /// the subgraph, or its highest version if the subgraph has no current version.
+    ///
+    /// A negative value means the indexing is behind, and a positive value means it is ahead
+    /// (e.g., indexing a pre-release version). Zero means the indexing is on the current version.
+    pub version_delta: i16,
 
     /// The largest allocation address.
     ///
@@ -59,11 +73,28 @@ pub struct Indexing {
     pub cost_model: Option<Ptr<CostModel>>,
 }
 
+impl Indexing {
+    /// Estimate the indexer's fee, in GRT wei, for the given query and variables.
+    ///
+    /// Returns `None` if the indexing has no cost model, the query cannot be parsed, or the cost
+    /// model evaluation fails.
+    pub fn estimate_fee(&self, query: &str, variables: &str) -> Option<u128> {
+        let cost_model = self.cost_model.as_ref()?;
+        let context = AgoraContext::new(query, variables).ok()?;
+        cost_model.cost_with_context(&context).ok()?.to_u128()
+    }
+}
+
 /// The [`IndexingStatus`] struct represents the indexer's indexing status.
 #[derive(Debug, Clone)]
 pub struct IndexingStatus {
     /// The latest block the indexer has indexed for the deployment.
     pub latest_block: BlockNumber,
+    /// The hash of the latest block the indexer has indexed for the deployment, if reported.
+    ///
+    /// Indexers at the same block height may be on different forks of the chain, the block hash
+    /// disambiguates them.
+    pub latest_block_hash: Option<BlockHash>,
     /// The minimum block the indexer has indexed for the deployment.
     pub min_block: Option<BlockNumber>,
 }
@@ -92,6 +123,12 @@ pub struct Indexer {
     /// Whether the indexer supports using Scalar TAP.
     pub scalar_tap_support: bool,
 
+    /// Whether the indexer's URL is reachable.
+    ///
+    /// Indexers are assumed to be reachable, unless the network service was configured to probe
+    /// the indexers' URLs and the probe failed.
+    pub reachable: bool,
+
     /// The indexer's indexings set.
     ///
     /// It is a set of deployment IDs that the indexer is indexing.
@@ -101,6 +138,20 @@ pub struct Indexer {
     pub staked_tokens: u128,
 }
 
+impl Indexer {
+    /// The receipt version to use when paying the indexer.
+    ///
+    /// Indexers supporting Scalar TAP are paid with TAP receipts, otherwise legacy receipts are
+    /// used.
+    pub fn receipt_version(&self) -> ReceiptVersion {
+        if self.scalar_tap_support {
+            ReceiptVersion::TAP
+        } else {
+            ReceiptVersion::Legacy
+        }
+    }
+}
+
 #[derive(Clone)]
 pub struct Subgraph {
     /// Subgraph ID
@@ -117,6 +168,9 @@ pub struct Subgraph {
     /// manifest.
     pub start_block: BlockNumber,
 
+    /// The subgraph's highest version deployment ID.
+    pub highest_version_deployment: DeploymentId,
+
     /// The subgraph's deployments.
     ///
     /// A list of deployment IDs known to be healthy and currently serving queries.
@@ -125,7 +179,7 @@ pub struct Subgraph {
     /// The subgraph's indexings.
     ///
     /// A table holding all the known healthy indexings for the subgraph.
-    pub indexings: HashMap<IndexingId, Indexing>,
+    pub indexings: HashMap<IndexingId, Arc<Indexing>>,
 }
 
 #[derive(Clone)]
@@ -143,6 +197,10 @@ pub struct Deployment {
     ///
     /// This field is extracted from the deployment manifest.
     pub start_block: BlockNumber,
+    /// The features declared in the deployment manifest (e.g., `fullTextSearch`, `grafting`).
+    ///
+    /// This field is extracted from the deployment manifest.
+    pub features: Vec<String>,
 
     /// A deployment may be associated with multiple subgraphs.
     pub subgraphs: HashSet<SubgraphId>,
@@ -150,7 +208,26 @@ pub struct Deployment {
     /// The deployment's indexings.
     ///
     /// A table holding all the known healthy indexings for the deployment.
-    pub indexings: HashMap<IndexingId, Indexing>,
+    pub indexings: HashMap<IndexingId, Arc<Indexing>>,
+}
+
+/// The [`SnapshotStats`] struct summarizes the network topology snapshot contents.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SnapshotStats {
+    /// The number of subgraphs.
+    pub subgraphs: usize,
+    /// The number of deployments.
+    pub deployments: usize,
+    /// The number of unique indexers.
+    pub indexers: usize,
+    /// The number of unique indexings.
+    pub indexings: usize,
+    /// The number of subgraphs transferred to L2.
+    pub transferred_subgraphs: usize,
+    /// The number of deployments transferred to L2.
+    pub transferred_deployments: usize,
+    /// The number of unique indexers supporting Scalar TAP.
+    pub scalar_tap_indexers: usize,
 }
 
 /// A snapshot of the network topology.
@@ -181,6 +258,133 @@ impl NetworkTopologySnapshot {
         self.deployments.get(id)
     }
 
+    /// Get the deployment's indexings that can be priced, i.e., that have a cost model.
+    ///
+    /// If the deployment is not found, it returns an empty list.
+    pub fn priceable_indexings(&self, deployment: &DeploymentId) -> Vec<&Indexing> {
+        self.deployments
+            .get(deployment)
+            .map(|deployment| {
+                deployment
+                    .indexings
+                    .values()
+                    .map(Arc::as_ref)
+                    .filter(|indexing| indexing.cost_model.is_some())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get the deployment's indexings whose indexer's graph-node version is greater than or equal
+    /// to the given minimum version.
+    ///
+    /// If the deployment is not found, it returns an empty list.
+    pub fn indexings_with_min_graph_node(
+        &self,
+        deployment: &DeploymentId,
+        min: &Version,
+    ) -> Vec<&Indexing> {
+        self.deployments
+            .get(deployment)
+            .map(|deployment| {
+                deployment
+                    .indexings
+                    .values()
+                    .map(Arc::as_ref)
+                    .filter(|indexing| indexing.indexer.graph_node_version >= *min)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// Get all the indexings of the given indexer, across all deployments.
+    ///
+    /// The indexings are deduplicated by [`IndexingId`]. If the indexer is not found, it returns an
+    /// empty list.
+    pub fn indexings_by_indexer(&self, indexer: &Address) -> Vec<&Indexing> {
+        let mut indexings = HashMap::new();
+
+        // The deployments table indexings take precedence, as they are not relative to any
+        // subgraph version.
+        let deployments_indexings = self
+            .deployments
+            .values()
+            .flat_map(|deployment| deployment.indexings.iter());
+        let subgraphs_indexings = self
+            .subgraphs
+            .values()
+            .flat_map(|subgraph| subgraph.indexings.iter());
+        for (id, indexing) in deployments_indexings.chain(subgraphs_indexings) {
+            if id.indexer == *indexer {
+                indexings.entry(*id).or_insert(indexing.as_ref());
+            }
+        }
+
+        indexings.into_values().collect()
+    }
+
+    /// Resolve the L2 [`SubgraphId`] of the given subgraph.
+    ///
+    /// If the subgraph was transferred to L2, it returns the subgraph ID on L2. Otherwise, it
+    /// returns `None`.
+    pub fn resolve_l2_subgraph(&self, id: &SubgraphId) -> Option<SubgraphId> {
+        self.transferred_subgraphs.get(id).copied()
+    }
+
+    /// Get the canonical [`SubgraphId`] of the given [`DeploymentId`].
+    ///
+    /// The canonical subgraph is the subgraph whose highest version points at the deployment. If
+    /// multiple subgraphs' highest versions point at the deployment, the one with the lowest
+    /// subgraph ID is returned, so the result is stable across snapshots.
+    ///
+    /// If the deployment is not found, or it is not the highest version of any subgraph, it
+    /// returns `None`.
+    pub fn canonical_subgraph_for_deployment(&self, id: &DeploymentId) -> Option<SubgraphId> {
+        self.deployments
+            .get(id)?
+            .subgraphs
+            .iter()
+            .filter(|subgraph_id| {
+                self.subgraphs
+                    .get(subgraph_id)
+                    .is_some_and(|subgraph| subgraph.highest_version_deployment == *id)
+            })
+            .min()
+            .copied()
+    }
+
+    /// Get the snapshot statistics summary.
+    pub fn stats(&self) -> SnapshotStats {
+        let indexings = self
+            .subgraphs
+            .values()
+            .flat_map(|subgraph| subgraph.indexings.values())
+            .chain(
+                self.deployments
+                    .values()
+                    .flat_map(|deployment| deployment.indexings.values()),
+            )
+            .map(|indexing| (indexing.id, &indexing.indexer))
+            .collect::<HashMap<_, _>>();
+        let indexers = indexings
+            .values()
+            .map(|indexer| (indexer.id, indexer.scalar_tap_support))
+            .collect::<HashMap<_, _>>();
+
+        SnapshotStats {
+            subgraphs: self.subgraphs.len(),
+            deployments: self.deployments.len(),
+            indexers: indexers.len(),
+            indexings: indexings.len(),
+            transferred_subgraphs: self.transferred_subgraphs.len(),
+            transferred_deployments: self.transferred_deployments.len(),
+            scalar_tap_indexers: indexers
+                .values()
+                .filter(|scalar_tap_support| **scalar_tap_support)
+                .count(),
+        }
+    }
+
     /// Get the snapshot subgraphs.
     pub fn subgraphs(&self) -> impl Deref<Target = HashMap<SubgraphId, Subgraph>> + '_ {
         &self.subgraphs
@@ -205,9 +409,17 @@ impl NetworkTopologySnapshot {
 }
 
 /// Construct the [`NetworkTopologySnapshot`] from the indexers and subgraphs information.
+///
+/// The deployments in `blocked_deployments` are excluded, along with their indexings. Subgraphs
+/// left without valid versions are excluded too.
+///
+/// The subgraphs and deployments manifest chain names are normalized with the `chain_aliases`
+/// map, see [`normalize_chain`].
 pub fn new_from(
     indexers_info: HashMap<Address, IndexerInfo>,
     subgraphs_info: HashMap<SubgraphId, SubgraphInfo>,
+    blocked_deployments: &HashSet<DeploymentId>,
+    chain_aliases: &BTreeMap<String, String>,
 ) -> NetworkTopologySnapshot {
     // Construct the deployments table
     let deployments_info = subgraphs_info
@@ -237,6 +449,7 @@ pub fn new_from(
                     indexer_agent_version: indexer.indexer_agent_version.clone(),
                     graph_node_version: indexer.graph_node_version.clone(),
                     scalar_tap_support: indexer_scalar_tap_support,
+                    reachable: indexer.reachable,
                     indexings: indexer.deployments.iter().copied().collect(),
                     staked_tokens: indexer.staked_tokens,
                 }),
@@ -248,12 +461,49 @@ pub fn new_from(
     let transferred_subgraphs = construct_transferred_subgraphs_table(&subgraphs_info);
     let transferred_deployments = construct_transferred_deployments_table(&deployments_info);
 
+    // Construct the indexings table
+    //
+    // This table holds a single canonical instance per indexing, shared by the subgraphs and
+    // deployments tables. The canonical indexing is not behind the highest version (i.e., it has no
+    // versions behind), subgraphs whose version is behind get their own adjusted instance.
+    let indexings = deployments_info
+        .iter()
+        .filter(|(deployment_id, _)| !blocked_deployments.contains(deployment_id))
+        .flat_map(|(deployment_id, deployment)| {
+            let indexers_info = &indexers_info;
+            let indexers = &indexers;
+            deployment.allocations.iter().filter_map(move |alloc| {
+                let indexing_indexer_id = alloc.indexer;
+                let _span = tracing::debug_span!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    "indexing",
+                    deployment.id = %deployment_id,
+                    indexer.id = %indexing_indexer_id,
+                )
+                .entered();
+
+                let indexing = construct_indexing(
+                    indexers_info,
+                    indexers,
+                    &indexing_indexer_id,
+                    deployment_id,
+                )?;
+                Some((indexing.id, Arc::new(indexing)))
+            })
+        })
+        .collect::<HashMap<_, _>>();
+
     // Construct the subgraphs table
     let subgraphs = subgraphs_info
         .into_iter()
         .filter_map(|(subgraph_id, subgraph)| {
             // If the subgraph is transferred to L2, exclude it
             if transferred_subgraphs.contains_key(&subgraph_id) {
+                tracing::debug!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    subgraph.id = %subgraph_id,
+                    "filtering-out subgraph: transferred to L2"
+                );
                 return None;
             }
 
@@ -265,13 +515,48 @@ pub fn new_from(
                     // Valid version must have a deployment with:
                     // - Valid manifest info (i.e., network).
                     // - Not marked as transferred to L2.
-                    version.deployment.manifest_network.is_some()
-                        && !transferred_deployments.contains(&version.deployment.id)
+                    // - Not in the deployments blocklist.
+                    if version.deployment.manifest_network.is_none() {
+                        tracing::debug!(
+                            target: SNAPSHOT_FILTER_TARGET,
+                            subgraph.id = %subgraph_id,
+                            subgraph.version = version.version,
+                            deployment.id = %version.deployment.id,
+                            "filtering-out subgraph version: missing manifest network"
+                        );
+                        return false;
+                    }
+                    if transferred_deployments.contains(&version.deployment.id) {
+                        tracing::debug!(
+                            target: SNAPSHOT_FILTER_TARGET,
+                            subgraph.id = %subgraph_id,
+                            subgraph.version = version.version,
+                            deployment.id = %version.deployment.id,
+                            "filtering-out subgraph version: deployment transferred to L2"
+                        );
+                        return false;
+                    }
+                    if blocked_deployments.contains(&version.deployment.id) {
+                        tracing::debug!(
+                            target: SNAPSHOT_FILTER_TARGET,
+                            subgraph.id = %subgraph_id,
+                            subgraph.version = version.version,
+                            deployment.id = %version.deployment.id,
+                            "filtering-out subgraph version: deployment blocked"
+                        );
+                        return false;
+                    }
+                    true
                 })
                 .collect::<Vec<_>>();
 
             // If all the subgraph's versions are invalid, exclude the subgraph.
             if versions.is_empty() {
+                tracing::debug!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    subgraph.id = %subgraph_id,
+                    "filtering-out subgraph: no valid versions"
+                );
                 return None;
             }
 
@@ -279,11 +564,12 @@ pub fn new_from(
             let highest_version = versions.first()?;
 
             let highest_version_number = highest_version.version;
-            let highest_version_deployment_manifest_chain = highest_version
-                .deployment
-                .manifest_network
-                .as_ref()?
-                .clone();
+            let current_version_number = subgraph.current_version.unwrap_or(highest_version_number);
+            let highest_version_deployment_id = highest_version.deployment.id;
+            let highest_version_deployment_manifest_chain = normalize_chain(
+                highest_version.deployment.manifest_network.as_ref()?,
+                chain_aliases,
+            );
             let highest_version_deployment_manifest_start_block =
                 highest_version.deployment.manifest_start_block.unwrap_or(0);
 
@@ -295,7 +581,12 @@ pub fn new_from(
                         .saturating_sub(version.version)
                         .try_into()
                         .unwrap_or(u8::MAX);
-                    (deployment_id, deployment_versions_behind)
+                    let deployment_version_delta =
+                        version_delta(current_version_number, version.version);
+                    (
+                        deployment_id,
+                        (deployment_versions_behind, deployment_version_delta),
+                    )
                 })
                 .collect::<HashMap<_, _>>();
 
@@ -303,72 +594,47 @@ pub fn new_from(
                 .into_iter()
                 .flat_map(|version| {
                     let deployment_id = version.deployment.id;
-                    let indexing_deployment_versions_behind = versions_behind_table
-                        .get(&deployment_id)
-                        .copied()
-                        .unwrap_or(u8::MAX);
+                    let (indexing_deployment_versions_behind, indexing_deployment_version_delta) =
+                        versions_behind_table
+                            .get(&deployment_id)
+                            .copied()
+                            .unwrap_or((u8::MAX, i16::MIN));
 
                     version
                         .deployment
                         .allocations
                         .into_iter()
                         .filter_map(|alloc| {
-                            // If the indexer is not in the indexers table, exclude it. It might
-                            // have been filtered out due to different reasons, e.g., invalid info.
-                            let indexing_indexer_id = alloc.indexer;
-                            let indexing_indexer_info = indexers_info.get(&indexing_indexer_id)?;
-
-                            // The indexer deployments list contains the healthy deployments. It
-                            // must contain the deployment ID, otherwise, that means it was filtered
-                            // out, e.g., invalid POI blocklist, etc.
-                            if !indexing_indexer_info.deployments.contains(&deployment_id) {
-                                return None;
-                            }
-
-                            let indexing_indexer = indexers.get(&indexing_indexer_id)?;
-
-                            // If the indexing has no allocations, exclude it
-                            let indexing_largest_allocation_addr = indexing_indexer_info
-                                .largest_allocation
-                                .get(&deployment_id)?;
-
-                            // If the indexing has no total allocated tokens, exclude it
-                            let indexing_total_allocated_tokens = indexing_indexer_info
-                                .total_allocated_tokens
-                                .get(&deployment_id)?;
-
-                            let indexing_status = indexing_indexer_info
-                                .indexings_progress
-                                .get(&deployment_id)
-                                .map(|status| IndexingStatus {
-                                    latest_block: status.latest_block,
-                                    min_block: status.min_block,
-                                });
-
-                            let indexing_cost_model = indexing_indexer_info
-                                .indexings_cost_model
-                                .get(&deployment_id)
-                                .cloned();
-
+                            // If the indexing was filtered-out, exclude it
                             let indexing_id = IndexingId {
-                                indexer: indexing_indexer_id,
+                                indexer: alloc.indexer,
                                 deployment: deployment_id,
                             };
+                            let indexing = indexings.get(&indexing_id)?;
+
+                            // Share the canonical indexing instance, unless the version is behind
+                            if indexing.versions_behind == indexing_deployment_versions_behind
+                                && indexing.version_delta == indexing_deployment_version_delta
+                            {
+                                return Some((indexing_id, indexing.clone()));
+                            }
+
                             let indexing = Indexing {
-                                id: indexing_id,
                                 versions_behind: indexing_deployment_versions_behind,
-                                largest_allocation: *indexing_largest_allocation_addr,
-                                total_allocated_tokens: *indexing_total_allocated_tokens,
-                                indexer: indexing_indexer.clone(),
-                                status: indexing_status,
-                                cost_model: indexing_cost_model,
+                                version_delta: indexing_deployment_version_delta,
+                                ..Indexing::clone(indexing)
                             };
-                            Some((indexing_id, indexing))
+                            Some((indexing_id, Arc::new(indexing)))
                         })
                         .collect::<Vec<_>>()
                 })
                 .collect::<HashMap<_, _>>();
             if subgraph_indexings.is_empty() {
+                tracing::debug!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    subgraph.id = %subgraph_id,
+                    "filtering-out subgraph: no indexings"
+                );
                 return None;
             }
 
@@ -377,6 +643,11 @@ pub fn new_from(
                 .map(|indexing_id| indexing_id.deployment)
                 .collect::<HashSet<_>>();
             if subgraph_deployments.is_empty() {
+                tracing::debug!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    subgraph.id = %subgraph_id,
+                    "filtering-out subgraph: no deployments"
+                );
                 return None;
             }
 
@@ -386,6 +657,7 @@ pub fn new_from(
                     id: subgraph.id,
                     chain: highest_version_deployment_manifest_chain,
                     start_block: highest_version_deployment_manifest_start_block,
+                    highest_version_deployment: highest_version_deployment_id,
                     deployments: subgraph_deployments,
                     indexings: subgraph_indexings,
                 },
@@ -399,69 +671,58 @@ pub fn new_from(
         .filter_map(|(deployment_id, deployment)| {
             // If the deployment is transferred to L2, exclude it
             if transferred_deployments.contains(&deployment_id) {
+                tracing::debug!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    deployment.id = %deployment_id,
+                    "filtering-out deployment: transferred to L2"
+                );
+                return None;
+            }
+
+            // If the deployment is in the blocklist, exclude it
+            if blocked_deployments.contains(&deployment_id) {
+                tracing::debug!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    deployment.id = %deployment_id,
+                    "filtering-out deployment: blocked"
+                );
                 return None;
             }
 
-            let deployment_versions_behind = 0;
-            let deployment_manifest_chain = deployment.manifest_network?.clone();
-            let deployment_manifest_start_block = deployment.manifest_start_block?;
+            let (deployment_manifest_chain, deployment_manifest_start_block) =
+                match (deployment.manifest_network, deployment.manifest_start_block) {
+                    (Some(chain), Some(start_block)) => {
+                        (normalize_chain(&chain, chain_aliases), start_block)
+                    }
+                    _ => {
+                        tracing::debug!(
+                            target: SNAPSHOT_FILTER_TARGET,
+                            deployment.id = %deployment_id,
+                            "filtering-out deployment: missing manifest network or start block"
+                        );
+                        return None;
+                    }
+                };
 
             let deployment_indexings = deployment
                 .allocations
                 .into_iter()
                 .filter_map(|alloc| {
-                    // If the indexer is not in the indexers table, exclude it. It might
-                    // have been filtered out due to different reasons, e.g., invalid info.
-                    let indexing_indexer_id = alloc.indexer;
-                    let indexing_indexer_info = indexers_info.get(&indexing_indexer_id)?;
-
-                    // The indexer deployments list contains the healthy deployments. It must
-                    // contain the deployment ID, otherwise, that means it was filtered out,
-                    // e.g., invalid POI blocklist, etc.
-                    if !indexing_indexer_info.deployments.contains(&deployment_id) {
-                        return None;
-                    }
-
-                    let indexing_indexer = indexers.get(&indexing_indexer_id)?;
-
-                    let indexing_largest_allocation_addr = indexing_indexer_info
-                        .largest_allocation
-                        .get(&deployment_id)?;
-
-                    let indexing_total_allocated_tokens = indexing_indexer_info
-                        .total_allocated_tokens
-                        .get(&deployment_id)?;
-
-                    let indexing_status = indexing_indexer_info
-                        .indexings_progress
-                        .get(&deployment_id)
-                        .map(|status| IndexingStatus {
-                            latest_block: status.latest_block,
-                            min_block: status.min_block,
-                        });
-
-                    let indexing_cost_model = indexing_indexer_info
-                        .indexings_cost_model
-                        .get(&deployment_id)
-                        .cloned();
-
+                    // If the indexing was filtered-out, exclude it
                     let indexing_id = IndexingId {
-                        indexer: indexing_indexer_id,
+                        indexer: alloc.indexer,
                         deployment: deployment_id,
                     };
-                    let indexing = Indexing {
-                        id: indexing_id,
-                        versions_behind: deployment_versions_behind,
-                        largest_allocation: *indexing_largest_allocation_addr,
-                        total_allocated_tokens: *indexing_total_allocated_tokens,
-                        indexer: indexing_indexer.clone(),
-                        status: indexing_status,
-                        cost_model: indexing_cost_model,
-                    };
-                    Some((indexing_id, indexing))
+                    let indexing = indexings.get(&indexing_id)?;
+                    Some((indexing_id, indexing.clone()))
                 })
                 .collect::<HashMap<_, _>>();
             if deployment_indexings.is_empty() {
+                tracing::debug!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    deployment.id = %deployment_id,
+                    "filtering-out deployment: no indexings"
+                );
                 return None;
             }
 
@@ -476,6 +737,11 @@ pub fn new_from(
                 })
                 .collect::<HashSet<_>>();
             if deployment_subgraphs.is_empty() {
+                tracing::debug!(
+                    target: SNAPSHOT_FILTER_TARGET,
+                    deployment.id = %deployment_id,
+                    "filtering-out deployment: no subgraphs"
+                );
                 return None;
             }
 
@@ -485,6 +751,7 @@ pub fn new_from(
                     id: deployment_id,
                     chain: deployment_manifest_chain,
                     start_block: deployment_manifest_start_block,
+                    features: deployment.manifest_features,
                     subgraphs: deployment_subgraphs,
                     indexings: deployment_indexings,
                 },
@@ -500,6 +767,107 @@ pub fn new_from(
     }
 }
 
+/// Construct the canonical [`Indexing`] of the given indexer and deployment.
+///
+/// If the indexer is not in the indexers table, the deployment is not among the indexer's healthy
+/// deployments, or the indexer has no allocations for the deployment, the indexing must be
+/// excluded and `None` is returned.
+fn construct_indexing(
+    indexers_info: &HashMap<Address, IndexerInfo>,
+    indexers: &HashMap<&Address, Arc<Indexer>>,
+    indexer_id: &Address,
+    deployment_id: &DeploymentId,
+) -> Option<Indexing> {
+    // If the indexer is not in the indexers table, exclude it. It might have been filtered out due
+    // to different reasons, e.g., invalid info.
+    let (Some(indexer_info), Some(indexer)) =
+        (indexers_info.get(indexer_id), indexers.get(indexer_id))
+    else {
+        tracing::debug!(
+            target: SNAPSHOT_FILTER_TARGET,
+            "filtering-out indexing: indexer not found"
+        );
+        return None;
+    };
+
+    // The indexer deployments list contains the healthy deployments. It must contain the
+    // deployment ID, otherwise, that means it was filtered out, e.g., invalid POI blocklist, etc.
+    if !indexer_info.deployments.contains(deployment_id) {
+        tracing::debug!(
+            target: SNAPSHOT_FILTER_TARGET,
+            "filtering-out indexing: deployment not in the indexer's healthy deployments"
+        );
+        return None;
+    }
+
+    // If the indexing has no allocations, exclude it
+    let Some(largest_allocation) = indexer_info.largest_allocation.get(deployment_id) else {
+        tracing::debug!(
+            target: SNAPSHOT_FILTER_TARGET,
+            "filtering-out indexing: no allocations"
+        );
+        return None;
+    };
+
+    // If the indexing has no total allocated tokens, exclude it
+    let Some(total_allocated_tokens) = indexer_info.total_allocated_tokens.get(deployment_id)
+    else {
+        tracing::debug!(
+            target: SNAPSHOT_FILTER_TARGET,
+            "filtering-out indexing: no allocated tokens"
+        );
+        return None;
+    };
+
+    let status = indexer_info
+        .indexings_progress
+        .get(deployment_id)
+        .map(|status| IndexingStatus {
+            latest_block: status.latest_block,
+            latest_block_hash: status.latest_block_hash,
+            min_block: status.min_block,
+        });
+
+    let cost_model = indexer_info
+        .indexings_cost_model
+        .get(deployment_id)
+        .cloned();
+
+    Some(Indexing {
+        id: IndexingId {
+            indexer: *indexer_id,
+            deployment: *deployment_id,
+        },
+        versions_behind: 0,
+        version_delta: 0,
+        largest_allocation: *largest_allocation,
+        total_allocated_tokens: *total_allocated_tokens,
+        indexer: indexer.clone(),
+        status,
+        cost_model,
+    })
+}
+
+/// Normalizes the manifest chain name to its canonical name.
+///
+/// Chain names found in the `aliases` map (e.g., `ethereum` or `eip155:1`) are replaced by their
+/// canonical name (e.g., `mainnet`). Unknown chain names are returned unchanged.
+fn normalize_chain(chain: &str, aliases: &BTreeMap<String, String>) -> String {
+    aliases
+        .get(chain)
+        .cloned()
+        .unwrap_or_else(|| chain.to_string())
+}
+
+/// Computes the signed difference between the given version and the reference version.
+///
+/// The result is negative if the version is behind the reference, and positive if it is ahead.
+/// Differences out of the `i16` range are clamped.
+fn version_delta(reference: u32, version: u32) -> i16 {
+    let delta = i64::from(version) - i64::from(reference);
+    delta.clamp(i16::MIN.into(), i16::MAX.into()) as i16
+}
+
 /// Extracts from the subgraphs info table the subgraph IDs that:
 /// - All its versions-deployments are marked as transferred to L2.
 /// - All its versions-deployments have no allocations.
@@ -543,3 +911,1112 @@ fn construct_transferred_deployments_table(
         })
         .collect::<HashSet<_>>()
 }
+
+#[cfg(test)]
+mod tests {
+    use vec1::Vec1;
+
+    use super::*;
+    use crate::network::internal::types::{
+        AllocationInfo, IndexerIndexingProgressInfo, SubgraphVersionInfo,
+    };
+
+    /// Test helper to parse a [`DeploymentId`] from a string.
+    fn test_deployment_id(id: &str) -> DeploymentId {
+        id.parse().expect("invalid deployment ID")
+    }
+
+    /// Test helper to parse a [`SubgraphId`] from a string.
+    fn test_subgraph_id(id: &str) -> SubgraphId {
+        id.parse().expect("invalid subgraph ID")
+    }
+
+    /// Test helper to build an [`IndexerInfo`] allocated to the given deployments.
+    ///
+    /// The allocation address of each deployment is derived from the indexer address.
+    fn test_indexer_info(id: Address, deployments: &[DeploymentId]) -> IndexerInfo {
+        IndexerInfo {
+            id,
+            url: "https://indexer.example.com/".parse().unwrap(),
+            staked_tokens: 100_000,
+            deployments: Vec1::try_from(deployments.to_vec()).expect("no deployments"),
+            indexer_agent_version: Version::new(1, 0, 0),
+            graph_node_version: Version::new(0, 35, 0),
+            reachable: true,
+            largest_allocation: deployments.iter().map(|d| (*d, id)).collect(),
+            total_allocated_tokens: deployments.iter().map(|d| (*d, 1_000)).collect(),
+            indexings_progress: Default::default(),
+            indexings_cost_model: Default::default(),
+        }
+    }
+
+    /// Test helper to build a [`DeploymentInfo`] with an allocation per indexer.
+    fn test_deployment_info(id: DeploymentId, indexers: &[Address]) -> DeploymentInfo {
+        DeploymentInfo {
+            id,
+            allocations: indexers
+                .iter()
+                .map(|indexer| AllocationInfo {
+                    id: *indexer,
+                    indexer: *indexer,
+                })
+                .collect(),
+            manifest_network: Some("mainnet".to_string()),
+            manifest_start_block: Some(0),
+            manifest_features: vec![],
+            transferred_to_l2: false,
+        }
+    }
+
+    /// Test helper to build a [`SubgraphInfo`] from a list of `(version, deployment)` pairs.
+    ///
+    /// The versions must be provided in descending order.
+    fn test_subgraph_info(id: SubgraphId, versions: Vec<(u32, DeploymentInfo)>) -> SubgraphInfo {
+        SubgraphInfo {
+            id,
+            id_on_l2: None,
+            current_version: None,
+            versions: Vec1::try_from(
+                versions
+                    .into_iter()
+                    .map(|(version, deployment)| SubgraphVersionInfo {
+                        version,
+                        deployment,
+                    })
+                    .collect::<Vec<_>>(),
+            )
+            .expect("no versions"),
+        }
+    }
+
+    #[test]
+    fn indexing_status_carries_latest_block_hash() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer_with_hash = Address::repeat_byte(0x01);
+        let indexer_without_hash = Address::repeat_byte(0x02);
+        let block_hash = BlockHash::repeat_byte(0xaa);
+
+        let mut indexer_info_with_hash = test_indexer_info(indexer_with_hash, &[deployment]);
+        indexer_info_with_hash.indexings_progress.insert(
+            deployment,
+            IndexerIndexingProgressInfo {
+                latest_block: 100,
+                latest_block_hash: Some(block_hash),
+                min_block: None,
+            },
+        );
+        let mut indexer_info_without_hash = test_indexer_info(indexer_without_hash, &[deployment]);
+        indexer_info_without_hash.indexings_progress.insert(
+            deployment,
+            IndexerIndexingProgressInfo {
+                latest_block: 100,
+                latest_block_hash: None,
+                min_block: None,
+            },
+        );
+
+        let indexers_info = HashMap::from([
+            (indexer_with_hash, indexer_info_with_hash),
+            (indexer_without_hash, indexer_info_without_hash),
+        ]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![(
+                    1,
+                    test_deployment_info(deployment, &[indexer_with_hash, indexer_without_hash]),
+                )],
+            ),
+        )]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        let latest_block_hash = |indexings: &HashMap<IndexingId, Arc<Indexing>>, indexer| {
+            indexings
+                .get(&IndexingId {
+                    indexer,
+                    deployment,
+                })
+                .expect("indexing not found")
+                .status
+                .as_ref()
+                .expect("indexing status not found")
+                .latest_block_hash
+        };
+
+        let subgraph = snapshot
+            .get_subgraph_by_id(&subgraph)
+            .expect("subgraph not found");
+        assert_eq!(
+            latest_block_hash(&subgraph.indexings, indexer_with_hash),
+            Some(block_hash)
+        );
+        assert_eq!(
+            latest_block_hash(&subgraph.indexings, indexer_without_hash),
+            None
+        );
+
+        let deployment = snapshot
+            .get_deployment_by_id(&deployment)
+            .expect("deployment not found");
+        assert_eq!(
+            latest_block_hash(&deployment.indexings, indexer_with_hash),
+            Some(block_hash)
+        );
+        assert_eq!(
+            latest_block_hash(&deployment.indexings, indexer_without_hash),
+            None
+        );
+    }
+
+    #[test]
+    fn deployment_carries_manifest_features() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer = Address::repeat_byte(0x01);
+
+        let indexers_info = HashMap::from([(indexer, test_indexer_info(indexer, &[deployment]))]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![(
+                    1,
+                    DeploymentInfo {
+                        manifest_features: vec!["fullTextSearch".to_string()],
+                        ..test_deployment_info(deployment, &[indexer])
+                    },
+                )],
+            ),
+        )]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        let deployment = snapshot
+            .get_deployment_by_id(&deployment)
+            .expect("deployment not found");
+        assert_eq!(deployment.features, vec!["fullTextSearch".to_string()]);
+    }
+
+    #[test]
+    fn version_delta_sign() {
+        //* Then
+        // Behind
+        assert_eq!(version_delta(3, 1), -2);
+        // Equal
+        assert_eq!(version_delta(3, 3), 0);
+        // Ahead
+        assert_eq!(version_delta(3, 4), 1);
+        // Clamped
+        assert_eq!(version_delta(u32::MAX, 0), i16::MIN);
+        assert_eq!(version_delta(0, u32::MAX), i16::MAX);
+    }
+
+    #[test]
+    fn indexings_version_delta() {
+        //* Given
+        let deployment_v1 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_v2 = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer = Address::repeat_byte(0x01);
+
+        let indexers_info = HashMap::from([(
+            indexer,
+            test_indexer_info(indexer, &[deployment_v1, deployment_v2]),
+        )]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![
+                    (2, test_deployment_info(deployment_v2, &[indexer])),
+                    (1, test_deployment_info(deployment_v1, &[indexer])),
+                ],
+            ),
+        )]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        let subgraph = snapshot
+            .get_subgraph_by_id(&subgraph)
+            .expect("subgraph not found");
+        let indexing = |deployment| {
+            subgraph
+                .indexings
+                .get(&IndexingId {
+                    indexer,
+                    deployment,
+                })
+                .expect("indexing not found")
+        };
+        assert_eq!(indexing(deployment_v2).version_delta, 0);
+        assert_eq!(indexing(deployment_v2).versions_behind, 0);
+        assert_eq!(indexing(deployment_v1).version_delta, -1);
+        assert_eq!(indexing(deployment_v1).versions_behind, 1);
+    }
+
+    #[test]
+    fn indexings_version_delta_against_current_version() {
+        //* Given
+        let deployment_v1 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_v2 = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let deployment_v3 = test_deployment_id("QmSLQfPFcz2pKRJZUH16Sk26EFpRgdxTYGnMiKvWgKRM2a");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer = Address::repeat_byte(0x01);
+
+        let indexers_info = HashMap::from([(
+            indexer,
+            test_indexer_info(indexer, &[deployment_v1, deployment_v2, deployment_v3]),
+        )]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            SubgraphInfo {
+                // Version 3 is a pre-release version
+                current_version: Some(2),
+                ..test_subgraph_info(
+                    subgraph,
+                    vec![
+                        (3, test_deployment_info(deployment_v3, &[indexer])),
+                        (2, test_deployment_info(deployment_v2, &[indexer])),
+                        (1, test_deployment_info(deployment_v1, &[indexer])),
+                    ],
+                )
+            },
+        )]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        let subgraph = snapshot
+            .get_subgraph_by_id(&subgraph)
+            .expect("subgraph not found");
+        let indexing = |deployment| {
+            subgraph
+                .indexings
+                .get(&IndexingId {
+                    indexer,
+                    deployment,
+                })
+                .expect("indexing not found")
+        };
+        // Ahead
+        assert_eq!(indexing(deployment_v3).version_delta, 1);
+        // Equal
+        assert_eq!(indexing(deployment_v2).version_delta, 0);
+        // Behind
+        assert_eq!(indexing(deployment_v1).version_delta, -1);
+    }
+
+    #[test]
+    fn canonical_subgraph_for_shared_deployment() {
+        //* Given
+        let deployment_v1 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_v2 = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let subgraph_a = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let subgraph_b = test_subgraph_id("CVHoVSrdiiYvLcH4wocDCazJ1YuixHZ1SKt34UWmnQcC");
+        let subgraph_c = test_subgraph_id("EMRitnR1t3drKrDQSmJMSmHBPB2sGotgZE12DzWNezDn");
+        let indexer = Address::repeat_byte(0x01);
+
+        let indexers_info = HashMap::from([(
+            indexer,
+            test_indexer_info(indexer, &[deployment_v1, deployment_v2]),
+        )]);
+        // Subgraphs A and B highest version point at the v2 deployment. Subgraph C points at the
+        // v2 deployment as an older version.
+        let subgraphs_info = HashMap::from([
+            (
+                subgraph_a,
+                test_subgraph_info(
+                    subgraph_a,
+                    vec![(1, test_deployment_info(deployment_v2, &[indexer]))],
+                ),
+            ),
+            (
+                subgraph_b,
+                test_subgraph_info(
+                    subgraph_b,
+                    vec![
+                        (2, test_deployment_info(deployment_v2, &[indexer])),
+                        (1, test_deployment_info(deployment_v1, &[indexer])),
+                    ],
+                ),
+            ),
+            (
+                subgraph_c,
+                test_subgraph_info(
+                    subgraph_c,
+                    vec![
+                        (2, test_deployment_info(deployment_v1, &[indexer])),
+                        (1, test_deployment_info(deployment_v2, &[indexer])),
+                    ],
+                ),
+            ),
+        ]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        assert_eq!(
+            snapshot.canonical_subgraph_for_deployment(&deployment_v2),
+            Some(std::cmp::min(subgraph_a, subgraph_b))
+        );
+        assert_eq!(
+            snapshot.canonical_subgraph_for_deployment(&deployment_v1),
+            Some(subgraph_c)
+        );
+        assert_eq!(
+            snapshot.canonical_subgraph_for_deployment(&test_deployment_id(
+                "QmSLQfPFcz2pKRJZUH16Sk26EFpRgdxTYGnMiKvWgKRM2a"
+            )),
+            None
+        );
+    }
+
+    #[test]
+    fn resolve_transferred_subgraph_to_l2() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let transferred_deployment =
+            test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let transferred_subgraph = test_subgraph_id("CVHoVSrdiiYvLcH4wocDCazJ1YuixHZ1SKt34UWmnQcC");
+        let subgraph_on_l2 = test_subgraph_id("EMRitnR1t3drKrDQSmJMSmHBPB2sGotgZE12DzWNezDn");
+        let indexer = Address::repeat_byte(0x01);
+
+        let indexers_info = HashMap::from([(indexer, test_indexer_info(indexer, &[deployment]))]);
+
+        // A subgraph is transferred to L2 if all its deployments are transferred and have no
+        // allocations.
+        let mut transferred_subgraph_info = test_subgraph_info(
+            transferred_subgraph,
+            vec![(
+                1,
+                DeploymentInfo {
+                    transferred_to_l2: true,
+                    ..test_deployment_info(transferred_deployment, &[])
+                },
+            )],
+        );
+        transferred_subgraph_info.id_on_l2 = Some(subgraph_on_l2);
+
+        let subgraphs_info = HashMap::from([
+            (
+                subgraph,
+                test_subgraph_info(
+                    subgraph,
+                    vec![(1, test_deployment_info(deployment, &[indexer]))],
+                ),
+            ),
+            (transferred_subgraph, transferred_subgraph_info),
+        ]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        assert_eq!(
+            snapshot.resolve_l2_subgraph(&transferred_subgraph),
+            Some(subgraph_on_l2)
+        );
+        assert_eq!(snapshot.resolve_l2_subgraph(&subgraph), None);
+    }
+
+    #[test]
+    fn priceable_indexings_have_cost_model() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let priceable_indexer = Address::repeat_byte(0x01);
+        let unpriceable_indexer = Address::repeat_byte(0x02);
+
+        let mut priceable_indexer_info = test_indexer_info(priceable_indexer, &[deployment]);
+        priceable_indexer_info.indexings_cost_model.insert(
+            deployment,
+            Ptr::new(CostModel::compile("default => 0.00001;", "").expect("invalid cost model")),
+        );
+
+        let indexers_info = HashMap::from([
+            (priceable_indexer, priceable_indexer_info),
+            (
+                unpriceable_indexer,
+                test_indexer_info(unpriceable_indexer, &[deployment]),
+            ),
+        ]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![(
+                    1,
+                    test_deployment_info(deployment, &[priceable_indexer, unpriceable_indexer]),
+                )],
+            ),
+        )]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        let indexings = snapshot.priceable_indexings(&deployment);
+        assert_eq!(indexings.len(), 1);
+        assert_eq!(indexings[0].id.indexer, priceable_indexer);
+        assert!(snapshot
+            .priceable_indexings(&test_deployment_id(
+                "QmSLQfPFcz2pKRJZUH16Sk26EFpRgdxTYGnMiKvWgKRM2a"
+            ))
+            .is_empty());
+    }
+
+    #[test]
+    fn estimate_indexing_fee() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer = Address::repeat_byte(0x01);
+        let indexer_without_cost_model = Address::repeat_byte(0x02);
+
+        let mut indexer_info = test_indexer_info(indexer, &[deployment]);
+        indexer_info.indexings_cost_model.insert(
+            deployment,
+            Ptr::new(
+                CostModel::compile("query { tokens { id } } => 0.5;\ndefault => 0.1;", "")
+                    .expect("invalid cost model"),
+            ),
+        );
+
+        let indexers_info = HashMap::from([
+            (indexer, indexer_info),
+            (
+                indexer_without_cost_model,
+                test_indexer_info(indexer_without_cost_model, &[deployment]),
+            ),
+        ]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![(
+                    1,
+                    test_deployment_info(deployment, &[indexer, indexer_without_cost_model]),
+                )],
+            ),
+        )]);
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        let deployment = snapshot
+            .get_deployment_by_id(&deployment)
+            .expect("deployment not found");
+        let indexing = |indexer| {
+            deployment
+                .indexings
+                .get(&IndexingId {
+                    indexer,
+                    deployment: deployment.id,
+                })
+                .expect("indexing not found")
+        };
+
+        //* When
+        let matched_fee = indexing(indexer).estimate_fee("{ tokens { id } }", "{}");
+        let default_fee = indexing(indexer).estimate_fee("{ pairs { id } }", "{}");
+        let invalid_query_fee = indexing(indexer).estimate_fee("{ tokens", "{}");
+        let no_cost_model_fee =
+            indexing(indexer_without_cost_model).estimate_fee("{ tokens { id } }", "{}");
+
+        //* Then
+        assert_eq!(matched_fee, Some(500_000_000_000_000_000));
+        assert_eq!(default_fee, Some(100_000_000_000_000_000));
+        assert_eq!(invalid_query_fee, None);
+        assert_eq!(no_cost_model_fee, None);
+    }
+
+    #[test]
+    fn indexings_filtered_by_min_graph_node_version() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer_below = Address::repeat_byte(0x01);
+        let indexer_at = Address::repeat_byte(0x02);
+        let indexer_above = Address::repeat_byte(0x03);
+
+        let test_indexer_info_with_version = |indexer, version| IndexerInfo {
+            graph_node_version: version,
+            ..test_indexer_info(indexer, &[deployment])
+        };
+        let indexers_info = HashMap::from([
+            (
+                indexer_below,
+                test_indexer_info_with_version(indexer_below, Version::new(0, 34, 1)),
+            ),
+            (
+                indexer_at,
+                test_indexer_info_with_version(indexer_at, Version::new(0, 35, 0)),
+            ),
+            (
+                indexer_above,
+                test_indexer_info_with_version(indexer_above, Version::new(0, 35, 1)),
+            ),
+        ]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![(
+                    1,
+                    test_deployment_info(deployment, &[indexer_below, indexer_at, indexer_above]),
+                )],
+            ),
+        )]);
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* When
+        let indexings =
+            snapshot.indexings_with_min_graph_node(&deployment, &Version::new(0, 35, 0));
+
+        //* Then
+        let indexers = indexings
+            .iter()
+            .map(|indexing| indexing.id.indexer)
+            .collect::<HashSet<_>>();
+        assert_eq!(indexers, HashSet::from([indexer_at, indexer_above]));
+    }
+
+    #[test]
+    fn indexer_receipt_version() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let legacy_indexer = Address::repeat_byte(0x01);
+        let tap_indexer = Address::repeat_byte(0x02);
+
+        let indexers_info = HashMap::from([
+            (
+                legacy_indexer,
+                IndexerInfo {
+                    indexer_agent_version: Version::new(0, 20, 0),
+                    ..test_indexer_info(legacy_indexer, &[deployment])
+                },
+            ),
+            (
+                tap_indexer,
+                IndexerInfo {
+                    indexer_agent_version: Version::new(1, 0, 0),
+                    ..test_indexer_info(tap_indexer, &[deployment])
+                },
+            ),
+        ]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![(
+                    1,
+                    test_deployment_info(deployment, &[legacy_indexer, tap_indexer]),
+                )],
+            ),
+        )]);
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* When
+        let receipt_version = |indexer| {
+            snapshot
+                .get_deployment_by_id(&deployment)
+                .unwrap()
+                .indexings[&IndexingId {
+                indexer,
+                deployment,
+            }]
+                .indexer
+                .receipt_version()
+        };
+
+        //* Then
+        assert_eq!(receipt_version(legacy_indexer), ReceiptVersion::Legacy);
+        assert_eq!(receipt_version(tap_indexer), ReceiptVersion::TAP);
+    }
+
+    #[test]
+    fn filtered_out_indexing_is_logged() {
+        use std::sync::Mutex;
+
+        use tracing_subscriber::{fmt::MakeWriter, EnvFilter};
+
+        /// Test writer capturing the logs into a shared buffer.
+        #[derive(Clone, Default)]
+        struct TestLogs(Arc<Mutex<Vec<u8>>>);
+
+        impl std::io::Write for TestLogs {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.0.lock().unwrap().extend_from_slice(buf);
+                Ok(buf.len())
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        impl<'a> MakeWriter<'a> for TestLogs {
+            type Writer = Self;
+
+            fn make_writer(&'a self) -> Self::Writer {
+                self.clone()
+            }
+        }
+
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let other_deployment = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer = Address::repeat_byte(0x01);
+        let unhealthy_indexer = Address::repeat_byte(0x02);
+
+        // The unhealthy indexer is allocated to the deployment, but the deployment is not among
+        // its healthy deployments, e.g., it was blocked by the POI blocklist.
+        let indexers_info = HashMap::from([
+            (indexer, test_indexer_info(indexer, &[deployment])),
+            (
+                unhealthy_indexer,
+                test_indexer_info(unhealthy_indexer, &[other_deployment]),
+            ),
+        ]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![(
+                    1,
+                    test_deployment_info(deployment, &[indexer, unhealthy_indexer]),
+                )],
+            ),
+        )]);
+
+        let logs = TestLogs::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_env_filter(EnvFilter::new(format!("{SNAPSHOT_FILTER_TARGET}=debug")))
+            .with_writer(logs.clone())
+            .with_ansi(false)
+            .finish();
+
+        //* When
+        let snapshot = tracing::subscriber::with_default(subscriber, || {
+            new_from(
+                indexers_info,
+                subgraphs_info,
+                &HashSet::new(),
+                &BTreeMap::new(),
+            )
+        });
+
+        //* Then
+        assert_eq!(
+            snapshot
+                .get_deployment_by_id(&deployment)
+                .expect("deployment not found")
+                .indexings
+                .len(),
+            1
+        );
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        let filtered_indexing_log = logs
+            .lines()
+            .find(|line| line.contains(&format!("indexer.id={unhealthy_indexer}")))
+            .expect("filtered-out indexing not logged");
+        assert!(filtered_indexing_log.contains(&format!("deployment.id={deployment}")));
+        assert!(filtered_indexing_log.contains(
+            "filtering-out indexing: deployment not in the indexer's healthy deployments"
+        ));
+        assert!(!logs.contains(&format!("indexer.id={indexer}")));
+    }
+
+    #[test]
+    fn snapshot_stats() {
+        //* Given
+        let deployment_v1 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_v2 = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let transferred_deployment =
+            test_deployment_id("QmSLQfPFcz2pKRJZUH16Sk26EFpRgdxTYGnMiKvWgKRM2a");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let transferred_subgraph = test_subgraph_id("CVHoVSrdiiYvLcH4wocDCazJ1YuixHZ1SKt34UWmnQcC");
+        let tap_indexer = Address::repeat_byte(0x01);
+        let legacy_indexer = Address::repeat_byte(0x02);
+
+        let indexers_info = HashMap::from([
+            (
+                tap_indexer,
+                test_indexer_info(tap_indexer, &[deployment_v1, deployment_v2]),
+            ),
+            (
+                legacy_indexer,
+                IndexerInfo {
+                    indexer_agent_version: Version::new(0, 9, 0),
+                    ..test_indexer_info(legacy_indexer, &[deployment_v2])
+                },
+            ),
+        ]);
+
+        let mut transferred_subgraph_info = test_subgraph_info(
+            transferred_subgraph,
+            vec![(
+                1,
+                DeploymentInfo {
+                    transferred_to_l2: true,
+                    ..test_deployment_info(transferred_deployment, &[])
+                },
+            )],
+        );
+        transferred_subgraph_info.id_on_l2 = Some(test_subgraph_id(
+            "EMRitnR1t3drKrDQSmJMSmHBPB2sGotgZE12DzWNezDn",
+        ));
+
+        let subgraphs_info = HashMap::from([
+            (
+                subgraph,
+                test_subgraph_info(
+                    subgraph,
+                    vec![
+                        (
+                            2,
+                            test_deployment_info(deployment_v2, &[tap_indexer, legacy_indexer]),
+                        ),
+                        (1, test_deployment_info(deployment_v1, &[tap_indexer])),
+                    ],
+                ),
+            ),
+            (transferred_subgraph, transferred_subgraph_info),
+        ]);
+
+        //* When
+        let stats = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        )
+        .stats();
+
+        //* Then
+        assert_eq!(
+            stats,
+            SnapshotStats {
+                subgraphs: 1,
+                deployments: 2,
+                indexers: 2,
+                indexings: 3,
+                transferred_subgraphs: 1,
+                transferred_deployments: 1,
+                scalar_tap_indexers: 1,
+            }
+        );
+    }
+
+    #[test]
+    fn indexings_are_shared_between_tables() {
+        //* Given
+        let deployment_v1 = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_v2 = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer = Address::repeat_byte(0x01);
+
+        let indexers_info = HashMap::from([(
+            indexer,
+            test_indexer_info(indexer, &[deployment_v1, deployment_v2]),
+        )]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![
+                    (2, test_deployment_info(deployment_v2, &[indexer])),
+                    (1, test_deployment_info(deployment_v1, &[indexer])),
+                ],
+            ),
+        )]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        let indexing = |indexings: &HashMap<IndexingId, Arc<Indexing>>, deployment| {
+            indexings
+                .get(&IndexingId {
+                    indexer,
+                    deployment,
+                })
+                .expect("indexing not found")
+                .clone()
+        };
+        let subgraph = snapshot
+            .get_subgraph_by_id(&subgraph)
+            .expect("subgraph not found");
+        let deployment_v1_indexings = &snapshot
+            .get_deployment_by_id(&deployment_v1)
+            .expect("deployment not found")
+            .indexings;
+        let deployment_v2_indexings = &snapshot
+            .get_deployment_by_id(&deployment_v2)
+            .expect("deployment not found")
+            .indexings;
+
+        // The highest version indexing is the same instance in both tables
+        assert!(Arc::ptr_eq(
+            &indexing(&subgraph.indexings, deployment_v2),
+            &indexing(deployment_v2_indexings, deployment_v2)
+        ));
+
+        // The subgraph's older version indexing is adjusted with the versions behind
+        let subgraph_indexing = indexing(&subgraph.indexings, deployment_v1);
+        let deployment_indexing = indexing(deployment_v1_indexings, deployment_v1);
+        assert!(!Arc::ptr_eq(&subgraph_indexing, &deployment_indexing));
+        assert_eq!(subgraph_indexing.versions_behind, 1);
+        assert_eq!(deployment_indexing.versions_behind, 0);
+        assert!(Arc::ptr_eq(
+            &subgraph_indexing.indexer,
+            &deployment_indexing.indexer
+        ));
+    }
+
+    #[test]
+    fn indexings_by_indexer_across_deployments() {
+        //* Given
+        let deployment_a = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment_b = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let subgraph_a = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let subgraph_b = test_subgraph_id("CVHoVSrdiiYvLcH4wocDCazJ1YuixHZ1SKt34UWmnQcC");
+        let indexer = Address::repeat_byte(0x01);
+        let other_indexer = Address::repeat_byte(0x02);
+
+        let indexers_info = HashMap::from([
+            (
+                indexer,
+                test_indexer_info(indexer, &[deployment_a, deployment_b]),
+            ),
+            (
+                other_indexer,
+                test_indexer_info(other_indexer, &[deployment_a]),
+            ),
+        ]);
+        let subgraphs_info = HashMap::from([
+            (
+                subgraph_a,
+                test_subgraph_info(
+                    subgraph_a,
+                    vec![(
+                        1,
+                        test_deployment_info(deployment_a, &[indexer, other_indexer]),
+                    )],
+                ),
+            ),
+            (
+                subgraph_b,
+                test_subgraph_info(
+                    subgraph_b,
+                    vec![(1, test_deployment_info(deployment_b, &[indexer]))],
+                ),
+            ),
+        ]);
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::new(),
+            &BTreeMap::new(),
+        );
+
+        //* When
+        let indexings = snapshot.indexings_by_indexer(&indexer);
+
+        //* Then
+        let deployments = indexings
+            .iter()
+            .map(|indexing| indexing.id.deployment)
+            .collect::<Vec<_>>();
+        assert_eq!(deployments.len(), 2);
+        assert!(deployments.contains(&deployment_a));
+        assert!(deployments.contains(&deployment_b));
+        assert!(indexings
+            .iter()
+            .all(|indexing| indexing.id.indexer == indexer));
+        assert!(snapshot
+            .indexings_by_indexer(&Address::repeat_byte(0x03))
+            .is_empty());
+    }
+
+    #[test]
+    fn blocked_deployments_are_excluded() {
+        //* Given
+        let blocked_deployment =
+            test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let deployment = test_deployment_id("QmawxQJ5U1JvgosoFVDyAwutLWxrckqVmBTQxaMaKoj3Lw");
+        let blocked_subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let subgraph = test_subgraph_id("9Co7EQe5PgW3ugCUJrJgRv4u9zdEuDJf8NvMWftNsBH8");
+        let indexer = Address::repeat_byte(0x01);
+
+        let indexers_info = HashMap::from([(
+            indexer,
+            test_indexer_info(indexer, &[blocked_deployment, deployment]),
+        )]);
+        let subgraphs_info = HashMap::from([
+            (
+                blocked_subgraph,
+                test_subgraph_info(
+                    blocked_subgraph,
+                    vec![(1, test_deployment_info(blocked_deployment, &[indexer]))],
+                ),
+            ),
+            (
+                subgraph,
+                test_subgraph_info(
+                    subgraph,
+                    vec![
+                        (2, test_deployment_info(deployment, &[indexer])),
+                        (1, test_deployment_info(blocked_deployment, &[indexer])),
+                    ],
+                ),
+            ),
+        ]);
+
+        //* When
+        let snapshot = new_from(
+            indexers_info,
+            subgraphs_info,
+            &HashSet::from([blocked_deployment]),
+            &BTreeMap::new(),
+        );
+
+        //* Then
+        assert!(snapshot.get_deployment_by_id(&blocked_deployment).is_none());
+        assert!(snapshot.get_subgraph_by_id(&blocked_subgraph).is_none());
+
+        let subgraph = snapshot
+            .get_subgraph_by_id(&subgraph)
+            .expect("subgraph not found");
+        assert_eq!(subgraph.deployments, HashSet::from([deployment]));
+        assert!(subgraph
+            .indexings
+            .keys()
+            .all(|indexing_id| indexing_id.deployment != blocked_deployment));
+        assert!(snapshot
+            .indexings_by_indexer(&indexer)
+            .iter()
+            .all(|indexing| indexing.id.deployment != blocked_deployment));
+    }
+    #[test]
+    fn normalize_chain_aliases() {
+        //* Given
+        let aliases = BTreeMap::from([
+            ("ethereum".to_string(), "mainnet".to_string()),
+            ("eip155:1".to_string(), "mainnet".to_string()),
+        ]);
+
+        //* Then
+        assert_eq!(normalize_chain("ethereum", &aliases), "mainnet");
+        assert_eq!(normalize_chain("eip155:1", &aliases), "mainnet");
+        assert_eq!(normalize_chain("mainnet", &aliases), "mainnet");
+        assert_eq!(normalize_chain("arbitrum-one", &aliases), "arbitrum-one");
+    }
+
+    #[test]
+    fn snapshot_chains_are_normalized() {
+        //* Given
+        let deployment = test_deployment_id("QmeYTH2fK2wv96XvnCGH2eyKFE8kmRfo53zYVy5dKysZtH");
+        let subgraph = test_subgraph_id("DZz4kDTdmzWLWsV373w2bSmoar3umKKH9y82SUKr5qmp");
+        let indexer = Address::repeat_byte(0x01);
+
+        let indexers_info = HashMap::from([(indexer, test_indexer_info(indexer, &[deployment]))]);
+        let subgraphs_info = HashMap::from([(
+            subgraph,
+            test_subgraph_info(
+                subgraph,
+                vec![(
+                    1,
+                    DeploymentInfo {
+                        manifest_network: Some("eip155:1".to_string()),
+                        ..test_deployment_info(deployment, &[indexer])
+                    },
+                )],
+            ),
+        )]);
+        let aliases = BTreeMap::from([("eip155:1".to_string(), "mainnet".to_string())]);
+
+        //* When
+        let snapshot = new_from(indexers_info, subgraphs_info, &HashSet::new(), &aliases);
+
+        //* Then
+        let subgraph = snapshot
+            .get_subgraph_by_id(&subgraph)
+            .expect("subgraph not found");
+        assert_eq!(subgraph.chain, "mainnet");
+        let deployment = snapshot
+            .get_deployment_by_id(&deployment)
+            .expect("deployment not found");
+        assert_eq!(deployment.chain, "mainnet");
+    }
+}
@@ -28,9 +28,15 @@ pub mod types {
         pub struct Subgraph {
             pub id: SubgraphId,
             pub id_on_l2: Option<SubgraphId>,
+            pub current_version: Option<CurrentSubgraphVersion>,
             pub versions: Vec<SubgraphVersion>,
         }
 
+        #[derive(Debug, Deserialize)]
+        pub struct CurrentSubgraphVersion {
+            pub version: u32,
+        }
+
         #[derive(Debug, Deserialize)]
         #[serde(rename_all = "camelCase")]
         pub struct SubgraphVersion {
@@ -161,6 +167,9 @@ impl Client {
             ) {{
                 id
                 {}
+                currentVersion {{
+                    version
+                }}
                 versions(orderBy: version, orderDirection: desc) {{
                     version
                     subgraphDeployment {{
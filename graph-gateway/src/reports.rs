@@ -1,35 +1,60 @@
+use std::time::Duration;
+
 use alloy_primitives::Address;
 use gateway_common::utils::timestamp::unix_timestamp;
 use gateway_framework::{
-    errors::{self, IndexerError},
-    reporting::{error_log, KafkaClient, CLIENT_REQUEST_TARGET, INDEXER_REQUEST_TARGET},
+    errors::IndexerError,
+    reporting::{error_log, KafkaClient, CLIENT_REQUEST_TARGET, INDEXER_REQUEST_TARGET, METRICS},
+    scalar::ScalarReceipt,
 };
 use prost::Message as _;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Map};
 use thegraph_core::types::attestation::Attestation;
 use toolshed::concat_bytes;
 
 use crate::indexer_client::ResponsePayload;
 
+/// The payment type of receipt-paid indexer requests.
+pub const PAYMENT_TYPE_RECEIPT: &str = "receipt";
+/// The payment type of subscription-paid indexer requests, which have no receipt.
+pub const PAYMENT_TYPE_SUBSCRIPTION: &str = "subscription";
+
+/// Returns the payment type of an indexer request, given the receipt attached to it.
+///
+/// Requests sent with a receipt are receipt-paid, whatever the client's auth method.
+pub fn payment_type(receipt: Option<&ScalarReceipt>) -> &'static str {
+    match receipt {
+        Some(_) => PAYMENT_TYPE_RECEIPT,
+        None => PAYMENT_TYPE_SUBSCRIPTION,
+    }
+}
+
+/// The fields of a client query report, collected from the client request span.
+#[derive(Deserialize)]
+struct ClientQueryFields {
+    request_id: String,
+    query_id: Option<String>,
+    graph_env: String,
+    gateway_id: Option<String>,
+    legacy_status_message: String,
+    legacy_status_code: u32,
+    start_time_ms: u64,
+    deployment: Option<String>,
+    user_address: Option<String>,
+    api_key: Option<String>,
+    subgraph_chain: Option<String>,
+    query_count: Option<u32>,
+    budget_grt: Option<f32>,
+    indexer_fees_grt: Option<f32>,
+    indexer_fees_usd: Option<f32>,
+    /// Set if the USD fees were computed from a stale GRT/USD exchange rate.
+    grt_per_usd_stale: Option<bool>,
+    client_region: Option<String>,
+}
+
 pub fn report_client_query(kafka: &KafkaClient, fields: Map<String, serde_json::Value>) {
-    #[derive(Deserialize)]
-    struct Fields {
-        request_id: String,
-        graph_env: String,
-        legacy_status_message: String,
-        legacy_status_code: u32,
-        start_time_ms: u64,
-        deployment: Option<String>,
-        user_address: Option<String>,
-        api_key: Option<String>,
-        subgraph_chain: Option<String>,
-        query_count: Option<u32>,
-        budget_grt: Option<f32>,
-        indexer_fees_grt: Option<f32>,
-        indexer_fees_usd: Option<f32>,
-    }
-    let fields = match serde_json::from_value::<Fields>(fields.into()) {
+    let fields = match serde_json::from_value::<ClientQueryFields>(fields.into()) {
         Ok(fields) => fields,
         Err(err) => {
             error_log(
@@ -50,7 +75,7 @@ pub fn report_client_query(kafka: &KafkaClient, fields: Map<String, serde_json::
         "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Nanos, true),
         "fields": {
             "message": "Client query result",
-            "query_id": &fields.request_id,
+            "query_id": fields.query_id.as_deref().unwrap_or(&fields.request_id),
             "ray_id": &fields.request_id, // In production this will be the Ray ID.
             "deployment": fields.deployment.as_deref().unwrap_or(""),
             "network": fields.subgraph_chain.as_deref().unwrap_or(""),
@@ -68,10 +93,24 @@ pub fn report_client_query(kafka: &KafkaClient, fields: Map<String, serde_json::
     .unwrap();
     println!("{log}");
 
-    let kafka_msg = json!({
-        "query_id": &fields.request_id,
+    let kafka_msg = client_query_kafka_msg(&fields, timestamp, response_time_ms);
+    kafka.send(
+        "gateway_client_query_results",
+        &serde_json::to_vec(&kafka_msg).unwrap(),
+    );
+}
+
+/// Builds the `gateway_client_query_results` Kafka message of a client query.
+fn client_query_kafka_msg(
+    fields: &ClientQueryFields,
+    timestamp: u64,
+    response_time_ms: u32,
+) -> serde_json::Value {
+    json!({
+        "query_id": fields.query_id.as_deref().unwrap_or(&fields.request_id),
         "ray_id": &fields.request_id, // In production this will be the Ray ID.
         "graph_env": &fields.graph_env,
+        "gateway_id": &fields.gateway_id,
         "timestamp": timestamp,
         "user": &fields.user_address,
         "api_key": &fields.api_key,
@@ -83,36 +122,60 @@ pub fn report_client_query(kafka: &KafkaClient, fields: Map<String, serde_json::
         "query_count": fields.query_count.unwrap_or(0),
         "fee": fields.indexer_fees_grt.unwrap_or(0.0),
         "fee_usd": fields.indexer_fees_usd.unwrap_or(0.0),
+        "fee_usd_stale": fields.grt_per_usd_stale.unwrap_or(false),
         "status": &fields.legacy_status_message,
         "status_code": fields.legacy_status_code,
-    });
-    kafka.send(
-        "gateway_client_query_results",
-        &serde_json::to_vec(&kafka_msg).unwrap(),
-    );
+        "client_region": &fields.client_region,
+    })
+}
+
+/// The fields of an indexer attempt report, collected from the indexer request span.
+#[derive(Deserialize)]
+struct IndexerAttemptFields {
+    request_id: String,
+    query_id: Option<String>,
+    graph_env: String,
+    gateway_id: Option<String>,
+    api_key: Option<String>,
+    user_address: Option<String>,
+    status_message: String,
+    status_code: u32,
+    /// The HTTP status of the indexer response, if one was received.
+    http_status: Option<u16>,
+    response_time_ms: u32,
+    deployment: String,
+    subgraph_chain: String,
+    indexer: String,
+    url: String,
+    blocks_behind: u64,
+    seconds_behind: Option<u32>,
+    freshness: Option<f64>,
+    fee_grt: Option<f32>,
+    legacy_scalar: Option<bool>,
+    allocation: Option<String>,
+    indexer_errors: Option<String>,
+    response_bytes: Option<u64>,
+    ttfb_ms: Option<u32>,
+    body_ms: Option<u32>,
+    payment_type: Option<String>,
+}
+
+impl IndexerAttemptFields {
+    /// The payment type of the indexer request, `receipt` or `subscription`.
+    ///
+    /// Defaults to `receipt`.
+    fn payment_type(&self) -> &str {
+        self.payment_type.as_deref().unwrap_or(PAYMENT_TYPE_RECEIPT)
+    }
+
+    /// The indexer fee, in GRT. Subscription-paid requests have no fees.
+    fn fee_grt(&self) -> f32 {
+        self.fee_grt.unwrap_or(0.0)
+    }
 }
 
 pub fn report_indexer_query(kafka: &KafkaClient, fields: Map<String, serde_json::Value>) {
-    #[derive(Deserialize)]
-    struct Fields {
-        request_id: String,
-        graph_env: String,
-        api_key: Option<String>,
-        user_address: Option<String>,
-        status_message: String,
-        status_code: u32,
-        response_time_ms: u32,
-        deployment: String,
-        subgraph_chain: String,
-        indexer: String,
-        url: String,
-        blocks_behind: u64,
-        fee_grt: f32,
-        legacy_scalar: Option<bool>,
-        allocation: Option<String>,
-        indexer_errors: Option<String>,
-    }
-    let fields = match serde_json::from_value::<Fields>(fields.into()) {
+    let fields = match serde_json::from_value::<IndexerAttemptFields>(fields.into()) {
         Ok(fields) => fields,
         Err(err) => {
             error_log(
@@ -123,6 +186,9 @@ pub fn report_indexer_query(kafka: &KafkaClient, fields: Map<String, serde_json:
         }
     };
 
+    let fee_grt_per_kb =
+        fee_grt_per_kb(fields.fee_grt() as f64, fields.response_bytes.unwrap_or(0));
+
     // data science: bigquery datasets still rely on this log line
     let log = serde_json::to_string(&json!({
         "target": INDEXER_REQUEST_TARGET,
@@ -130,15 +196,19 @@ pub fn report_indexer_query(kafka: &KafkaClient, fields: Map<String, serde_json:
         "timestamp": chrono::Utc::now().to_rfc3339_opts(chrono::SecondsFormat::Nanos, true),
         "fields": {
             "message": "Indexer attempt",
-            "query_id": &fields.request_id,
+            "query_id": fields.query_id.as_deref().unwrap_or(&fields.request_id),
             "ray_id": &fields.request_id, // In production this will be the Ray ID.
             "deployment": &fields.deployment,
             "indexer": &fields.indexer,
             "url": &fields.url,
             "blocks_behind": fields.blocks_behind,
+            "seconds_behind": fields.seconds_behind.unwrap_or(0),
+            "freshness": fields.freshness,
             "attempt_index": 0,
             "api_key": fields.api_key.as_deref().unwrap_or(""),
-            "fee": fields.fee_grt,
+            "fee": fields.fee_grt(),
+            "fee_per_kb": fee_grt_per_kb,
+            "payment_type": fields.payment_type(),
             "response_time_ms": fields.response_time_ms,
             "allocation": &fields.allocation,
             "indexer_errors": &fields.indexer_errors,
@@ -149,10 +219,23 @@ pub fn report_indexer_query(kafka: &KafkaClient, fields: Map<String, serde_json:
     .unwrap();
     println!("{log}");
 
-    let kafka_msg = json!({
-        "query_id": &fields.request_id,
+    let kafka_msg = indexer_attempt_kafka_msg(&fields, fee_grt_per_kb);
+    kafka.send(
+        "gateway_indexer_attempts",
+        &serde_json::to_vec(&kafka_msg).unwrap(),
+    );
+}
+
+/// Builds the `gateway_indexer_attempts` Kafka message of an indexer attempt.
+fn indexer_attempt_kafka_msg(
+    fields: &IndexerAttemptFields,
+    fee_grt_per_kb: f64,
+) -> serde_json::Value {
+    json!({
+        "query_id": fields.query_id.as_deref().unwrap_or(&fields.request_id),
         "ray_id": &fields.request_id, // In production this will be the Ray ID.
         "graph_env": &fields.graph_env,
+        "gateway_id": &fields.gateway_id,
         "timestamp": unix_timestamp(),
         "api_key": fields.api_key.as_deref().unwrap_or(""),
         "user_address": fields.user_address.as_deref().unwrap_or(""),
@@ -160,41 +243,47 @@ pub fn report_indexer_query(kafka: &KafkaClient, fields: Map<String, serde_json:
         "network": &fields.subgraph_chain,
         "indexer": &fields.indexer,
         "url": &fields.url,
-        "fee": fields.fee_grt,
+        "fee": fields.fee_grt(),
+        "fee_per_kb": fee_grt_per_kb,
+        "payment_type": fields.payment_type(),
+        "response_bytes": fields.response_bytes.unwrap_or(0),
         "legacy_scalar": fields.legacy_scalar.unwrap_or(false),
         "utility": 1.0,
         "blocks_behind": fields.blocks_behind,
+        "seconds_behind": fields.seconds_behind.unwrap_or(0),
+        "freshness": fields.freshness,
         "response_time_ms": fields.response_time_ms,
-        "allocation": fields.allocation.as_deref().unwrap_or(""),
+        "ttfb_ms": fields.ttfb_ms,
+        "body_ms": fields.body_ms,
+        "allocation": &fields.allocation,
         "indexer_errors": fields.indexer_errors.as_deref().unwrap_or(""),
         "status": &fields.status_message,
         "status_code": fields.status_code,
-    });
-    kafka.send(
-        "gateway_indexer_attempts",
-        &serde_json::to_vec(&kafka_msg).unwrap(),
-    );
+        "http_status": fields.http_status,
+    })
 }
 
-pub fn legacy_status<T>(result: &Result<T, errors::Error>) -> (String, u32) {
-    match result {
-        Ok(_) => ("200 OK".to_string(), 0),
-        Err(err) => match err {
-            errors::Error::BlockNotFound(_) => ("Unresolved block".to_string(), 604610595),
-            errors::Error::Internal(_) => ("Internal error".to_string(), 816601499),
-            errors::Error::Auth(_) => ("Invalid API key".to_string(), 888904173),
-            errors::Error::BadQuery(_) => ("Invalid query".to_string(), 595700117),
-            errors::Error::NoIndexers => (
-                "No indexers found for subgraph deployment".to_string(),
-                1621366907,
-            ),
-            errors::Error::BadIndexers(_) => (
-                "No suitable indexer found for subgraph deployment".to_string(),
-                510359393,
-            ),
-            errors::Error::SubgraphNotFound(_) => (err.to_string(), 2599148187),
-        },
+/// Returns the indexer fee per kilobyte of response, or 0.0 for an empty response.
+pub fn fee_grt_per_kb(fee_grt: f64, response_bytes: u64) -> f64 {
+    if response_bytes == 0 {
+        return 0.0;
     }
+    fee_grt / (response_bytes as f64 / 1_000.0)
+}
+
+/// Returns a freshness score in `[0, 1]` for an indexer `seconds_behind` chain head.
+///
+/// The lag is normalized by the chain's block time, so the score is comparable across chains: an
+/// indexer at chain head scores 1.0, and one block behind scores 0.5.
+pub fn freshness_score(seconds_behind: u32, block_time: Duration) -> f64 {
+    if seconds_behind == 0 {
+        return 1.0;
+    }
+    if block_time.is_zero() {
+        return 0.0;
+    }
+    let blocks_behind = seconds_behind as f64 / block_time.as_secs_f64();
+    1.0 / (1.0 + blocks_behind)
 }
 
 // Like much of this file. This is maintained is a partially backward-compatible state for data
@@ -205,7 +294,11 @@ pub fn indexer_attempt_status_code(result: &Result<ResponsePayload, IndexerError
         Err(IndexerError::Internal(_)) => (0x1, 0x0),
         Err(IndexerError::Unavailable(_)) => (0x2, 0x0),
         Err(IndexerError::Timeout) => (0x3, 0x0),
-        Err(IndexerError::BadResponse(_)) => (0x4, 0x0),
+        Err(
+            IndexerError::BadResponse(_)
+            | IndexerError::NoAttestation
+            | IndexerError::UnexpectedPayload(_),
+        ) => (0x4, 0x0),
     };
     (prefix << 28) | (data & (u32::MAX >> 4))
 }
@@ -218,9 +311,25 @@ pub fn serialize_attestation(
 ) -> Vec<u8> {
     // Limit string payloads to 10 KB.
     const MAX_LEN: usize = 10_000;
+    let request = (request.len() <= MAX_LEN).then_some(request);
+    // Oversized JSON responses are trimmed to fit, instead of being dropped.
+    let (response, response_omitted_elements) = if response.len() <= MAX_LEN {
+        (Some(response), None)
+    } else {
+        match trim_json_to_bytes(&response, MAX_LEN) {
+            (trimmed, _) if trimmed.is_empty() => (None, None),
+            (trimmed, omitted) => (Some(trimmed), Some(omitted as u32)),
+        }
+    };
+    for payload in [&request, &response] {
+        if payload.is_none() {
+            METRICS.attestations.dropped_payloads.inc();
+        }
+    }
     AttestationProtobuf {
-        request: (request.len() <= MAX_LEN).then_some(request),
-        response: (response.len() <= MAX_LEN).then_some(response),
+        request,
+        response,
+        response_omitted_elements,
         allocation: allocation.0 .0.into(),
         subgraph_deployment: attestation.deployment.0.into(),
         request_cid: attestation.request_cid.0.into(),
@@ -230,7 +339,11 @@ pub fn serialize_attestation(
     .encode_to_vec()
 }
 
-#[derive(Clone, PartialEq, prost::Message)]
+/// The attestation report message.
+///
+/// It also implements [`serde::Serialize`], with the byte fields hex-encoded, so it can be written
+/// to human-readable audit logs.
+#[derive(Clone, PartialEq, prost::Message, Serialize)]
 pub struct AttestationProtobuf {
     #[prost(string, optional, tag = "1")]
     request: Option<String>,
@@ -238,17 +351,429 @@ pub struct AttestationProtobuf {
     response: Option<String>,
     /// 20 bytes
     #[prost(bytes, tag = "3")]
+    #[serde(serialize_with = "serialize_hex")]
     allocation: Vec<u8>,
     /// 32 bytes
     #[prost(bytes, tag = "4")]
+    #[serde(serialize_with = "serialize_hex")]
     subgraph_deployment: Vec<u8>,
     /// 32 bytes
     #[prost(bytes, tag = "5")]
+    #[serde(serialize_with = "serialize_hex")]
     request_cid: Vec<u8>,
     /// 32 bytes
     #[prost(bytes, tag = "6")]
+    #[serde(serialize_with = "serialize_hex")]
     response_cid: Vec<u8>,
     /// 65 bytes, ECDSA signature (v, r, s)
     #[prost(bytes, tag = "7")]
+    #[serde(serialize_with = "serialize_hex")]
     signature: Vec<u8>,
+    /// The number of array elements omitted from the response to fit the payload limit, if it
+    /// was trimmed
+    #[prost(uint32, optional, tag = "8")]
+    response_omitted_elements: Option<u32>,
+}
+
+/// Serialize the bytes as a `0x`-prefixed hex string.
+fn serialize_hex<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&alloy_primitives::hex::encode_prefixed(bytes))
+}
+
+/// Trim the JSON body to at most `max` bytes by removing elements from its arrays, largest array
+/// first, preserving the document structure.
+///
+/// Returns the trimmed JSON and the number of array elements omitted. If the body is not JSON, or
+/// it cannot be trimmed to fit, an empty string is returned.
+pub fn trim_json_to_bytes(body: &str, max: usize) -> (String, usize) {
+    if body.len() <= max {
+        return (body.to_string(), 0);
+    }
+    let mut value: serde_json::Value = match serde_json::from_str(body) {
+        Ok(value) => value,
+        Err(_) => return (String::new(), 0),
+    };
+
+    let mut omitted = 0;
+    loop {
+        let trimmed = value.to_string();
+        if trimmed.len() <= max {
+            return (trimmed, omitted);
+        }
+        let mut excess = trimmed.len() - max;
+
+        let mut largest = None;
+        largest_json_array(&value, String::new(), &mut largest);
+        let items = match largest
+            .and_then(|(_, pointer)| value.pointer_mut(&pointer))
+            .and_then(|array| array.as_array_mut())
+        {
+            Some(items) => items,
+            None => return (String::new(), 0),
+        };
+        // Remove enough elements to cover the excess, including their separators.
+        while let Some(item) = items.pop() {
+            omitted += 1;
+            let removed = item.to_string().len() + 1;
+            if removed >= excess {
+                break;
+            }
+            excess -= removed;
+        }
+    }
+}
+
+/// Find the JSON pointer of the non-empty array with the most elements in the value.
+fn largest_json_array(
+    value: &serde_json::Value,
+    pointer: String,
+    largest: &mut Option<(usize, String)>,
+) {
+    match value {
+        serde_json::Value::Array(items) => {
+            for (index, item) in items.iter().enumerate() {
+                largest_json_array(item, format!("{pointer}/{index}"), largest);
+            }
+            if !items.is_empty() && largest.as_ref().map_or(true, |(len, _)| items.len() > *len) {
+                *largest = Some((items.len(), pointer));
+            }
+        }
+        serde_json::Value::Object(fields) => {
+            for (key, field) in fields {
+                let key = key.replace('~', "~0").replace('/', "~1");
+                largest_json_array(field, format!("{pointer}/{key}"), largest);
+            }
+        }
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use alloy_primitives::Address;
+    use gateway_framework::{reporting::METRICS, scalar::ScalarReceipt};
+    use prost::Message as _;
+    use serde_json::json;
+    use thegraph_core::types::attestation::Attestation;
+
+    use super::{
+        client_query_kafka_msg, fee_grt_per_kb, freshness_score, indexer_attempt_kafka_msg,
+        payment_type, serialize_attestation, trim_json_to_bytes, AttestationProtobuf,
+        ClientQueryFields, IndexerAttemptFields,
+    };
+
+    #[test]
+    fn serialize_attestation_drops_oversized_payloads() {
+        //* Given
+        let attestation = Attestation {
+            request_cid: Default::default(),
+            response_cid: Default::default(),
+            deployment: Default::default(),
+            r: Default::default(),
+            s: Default::default(),
+            v: 0,
+        };
+        let request = "{}".to_string();
+        let response = "a".repeat(10_001);
+        let dropped_before = METRICS.attestations.dropped_payloads.get();
+
+        //* When
+        let payload = serialize_attestation(&attestation, Address::default(), request, response);
+
+        //* Then
+        let payload = AttestationProtobuf::decode(payload.as_slice()).unwrap();
+        assert_eq!(payload.request.as_deref(), Some("{}"));
+        assert_eq!(payload.response, None);
+        assert_eq!(
+            METRICS.attestations.dropped_payloads.get(),
+            dropped_before + 1
+        );
+    }
+
+    #[test]
+    fn trim_large_json_array_response() {
+        //* Given
+        let tokens = (0..1_000)
+            .map(|i| json!({ "id": format!("0x{i:040x}"), "symbol": "GRT" }))
+            .collect::<Vec<_>>();
+        let response = json!({ "data": { "tokens": tokens } }).to_string();
+        let max = 10_000;
+        assert!(response.len() > max);
+
+        //* When
+        let (trimmed, omitted) = trim_json_to_bytes(&response, max);
+
+        //* Then
+        assert!(trimmed.len() <= max, "trimmed length: {}", trimmed.len());
+        let trimmed: serde_json::Value = serde_json::from_str(&trimmed).unwrap();
+        let remaining = trimmed["data"]["tokens"].as_array().unwrap();
+        assert!(!remaining.is_empty());
+        assert_eq!(remaining.len() + omitted, 1_000);
+        assert_eq!(remaining[..], tokens[..remaining.len()]);
+    }
+
+    #[test]
+    fn serialize_attestation_trims_oversized_json_response() {
+        //* Given
+        let attestation = Attestation {
+            request_cid: Default::default(),
+            response_cid: Default::default(),
+            deployment: Default::default(),
+            r: Default::default(),
+            s: Default::default(),
+            v: 0,
+        };
+        let response = json!({ "data": { "values": vec![1_000_000; 5_000] } }).to_string();
+
+        //* When
+        let payload =
+            serialize_attestation(&attestation, Address::default(), "{}".to_string(), response);
+
+        //* Then
+        let payload = AttestationProtobuf::decode(payload.as_slice()).unwrap();
+        let response = payload.response.unwrap();
+        assert!(response.len() <= 10_000);
+        let omitted = payload.response_omitted_elements.unwrap() as usize;
+        let response: serde_json::Value = serde_json::from_str(&response).unwrap();
+        assert_eq!(
+            response["data"]["values"].as_array().unwrap().len() + omitted,
+            5_000
+        );
+    }
+
+    #[test]
+    fn fee_per_kb() {
+        assert_eq!(fee_grt_per_kb(0.002, 4_000), 0.0005);
+        assert_eq!(fee_grt_per_kb(0.002, 0), 0.0);
+        assert_eq!(fee_grt_per_kb(0.0, 0), 0.0);
+    }
+
+    #[test]
+    fn freshness_is_normalized_by_chain_block_time() {
+        //* Given
+        let seconds_behind = 12;
+        let mainnet_block_time = Duration::from_secs(12);
+        let arbitrum_block_time = Duration::from_millis(250);
+
+        //* When
+        let mainnet = freshness_score(seconds_behind, mainnet_block_time);
+        let arbitrum = freshness_score(seconds_behind, arbitrum_block_time);
+
+        //* Then
+        assert_eq!(mainnet, 0.5);
+        assert_eq!(arbitrum, 1.0 / 49.0);
+        assert!(mainnet > arbitrum);
+        assert_eq!(freshness_score(0, arbitrum_block_time), 1.0);
+    }
+
+    #[test]
+    fn report_subscription_authorized_indexer_attempt_fee() {
+        //* Given
+        // Subscription-authorized queries are still sent to the indexers with a receipt
+        let receipt = ScalarReceipt::Legacy(500_000_000_000_000_000, vec![0x01; 164]);
+        let fields = json!({
+            "request_id": "test-request",
+            "graph_env": "test",
+            "status_message": "200 OK",
+            "status_code": 0,
+            "response_time_ms": 100,
+            "deployment": "QmQqLJVgZLcRduoszARzRi12qGheUTWAHFf3ixMeGm2xML",
+            "subgraph_chain": "mainnet",
+            "indexer": format!("{:?}", Address::repeat_byte(0x01)),
+            "url": "https://indexer.example.com/",
+            "blocks_behind": 0,
+            "response_bytes": 1_000,
+            "fee_grt": (receipt.grt_value() as f64 * 1e-18) as f32,
+            "allocation": format!("{:?}", receipt.allocation()),
+            "legacy_scalar": true,
+            "payment_type": payment_type(Some(&receipt)),
+        });
+        let fields: IndexerAttemptFields = serde_json::from_value(fields).unwrap();
+
+        //* When
+        let fee_per_kb = fee_grt_per_kb(fields.fee_grt() as f64, 1_000);
+        let msg = indexer_attempt_kafka_msg(&fields, fee_per_kb);
+
+        //* Then
+        assert_eq!(msg["payment_type"], "receipt");
+        assert_eq!(msg["fee"], 0.5);
+        assert_eq!(msg["fee_per_kb"], 0.5);
+        assert_eq!(
+            msg["allocation"],
+            format!("{:?}", Address::repeat_byte(0x01))
+        );
+        assert_eq!(msg["legacy_scalar"], true);
+    }
+
+    #[test]
+    fn report_indexer_attempt_http_status() {
+        //* Given
+        let fields = |http_status: u16| {
+            let fields = json!({
+                "request_id": "test-request",
+                "graph_env": "test",
+                "status_message": "200 OK",
+                "status_code": 0,
+                "http_status": http_status,
+                "response_time_ms": 100,
+                "deployment": "QmQqLJVgZLcRduoszARzRi12qGheUTWAHFf3ixMeGm2xML",
+                "subgraph_chain": "mainnet",
+                "indexer": format!("{:?}", Address::repeat_byte(0x01)),
+                "url": "https://indexer.example.com/",
+                "blocks_behind": 0,
+            });
+            serde_json::from_value::<IndexerAttemptFields>(fields).unwrap()
+        };
+
+        //* When
+        let ok = indexer_attempt_kafka_msg(&fields(200), 0.0);
+        let partial = indexer_attempt_kafka_msg(&fields(206), 0.0);
+
+        //* Then
+        assert_eq!(ok["status"], "200 OK");
+        assert_eq!(partial["status"], "200 OK");
+        assert_eq!(ok["status_code"], partial["status_code"]);
+        assert_eq!(ok["http_status"], 200);
+        assert_eq!(partial["http_status"], 206);
+    }
+
+    #[test]
+    fn report_graph_env_and_gateway_id_independently() {
+        //* Given
+        let fields = json!({
+            "request_id": "test-request",
+            "graph_env": "mainnet",
+            "gateway_id": "fe3c0304-7383-48f4-9f3a-fc0cb37f55ba",
+            "status_message": "200 OK",
+            "status_code": 0,
+            "response_time_ms": 100,
+            "deployment": "QmQqLJVgZLcRduoszARzRi12qGheUTWAHFf3ixMeGm2xML",
+            "subgraph_chain": "mainnet",
+            "indexer": format!("{:?}", Address::repeat_byte(0x01)),
+            "url": "https://indexer.example.com/",
+            "blocks_behind": 0,
+        });
+        let fields: IndexerAttemptFields = serde_json::from_value(fields).unwrap();
+
+        //* When
+        let msg = indexer_attempt_kafka_msg(&fields, 0.0);
+
+        //* Then
+        assert_eq!(msg["graph_env"], "mainnet");
+        assert_eq!(msg["gateway_id"], "fe3c0304-7383-48f4-9f3a-fc0cb37f55ba");
+    }
+
+    #[test]
+    fn report_client_region() {
+        //* Given
+        let fields = |client_region: Option<&str>| {
+            let fields = json!({
+                "request_id": "test-request",
+                "graph_env": "test",
+                "legacy_status_message": "200 OK",
+                "legacy_status_code": 0,
+                "start_time_ms": 0,
+                "client_region": client_region,
+            });
+            serde_json::from_value::<ClientQueryFields>(fields).unwrap()
+        };
+
+        //* When
+        let with_region = client_query_kafka_msg(&fields(Some("DE")), 100, 100);
+        let without_region = client_query_kafka_msg(&fields(None), 100, 100);
+
+        //* Then
+        assert_eq!(with_region["client_region"], "DE");
+        assert!(without_region["client_region"].is_null());
+    }
+
+    #[test]
+    fn report_client_query_failed_before_indexer_requests() {
+        //* Given
+        let fields = json!({
+            "request_id": "test-request",
+            "graph_env": "test",
+            "status_message": "auth error: missing authorization header",
+            "legacy_status_message": "Invalid API key",
+            "legacy_status_code": 888904173,
+            "start_time_ms": 0,
+        });
+        let fields: ClientQueryFields = serde_json::from_value(fields).unwrap();
+
+        //* When
+        let msg = client_query_kafka_msg(&fields, 100, 100);
+
+        //* Then
+        assert_eq!(msg["status"], "Invalid API key");
+        assert_eq!(msg["status_code"], 888904173);
+        assert_eq!(msg["fee"], 0.0);
+        assert_eq!(msg["fee_usd"], 0.0);
+        assert_eq!(msg["query_count"], 0);
+        assert_eq!(msg["deployment"], "");
+    }
+
+    #[test]
+    fn report_stale_exchange_rate() {
+        //* Given
+        let fields = |grt_per_usd_stale: Option<bool>| {
+            let fields = json!({
+                "request_id": "test-request",
+                "graph_env": "test",
+                "legacy_status_message": "200 OK",
+                "legacy_status_code": 0,
+                "start_time_ms": 0,
+                "indexer_fees_usd": 0.001,
+                "grt_per_usd_stale": grt_per_usd_stale,
+            });
+            serde_json::from_value::<ClientQueryFields>(fields).unwrap()
+        };
+
+        //* When
+        let stale = client_query_kafka_msg(&fields(Some(true)), 100, 100);
+        let fresh = client_query_kafka_msg(&fields(Some(false)), 100, 100);
+        let unknown = client_query_kafka_msg(&fields(None), 100, 100);
+
+        //* Then
+        assert_eq!(stale["fee_usd_stale"], true);
+        assert_eq!(fresh["fee_usd_stale"], false);
+        assert_eq!(unknown["fee_usd_stale"], false);
+    }
+
+    #[test]
+    fn serialize_attestation_to_json() {
+        //* Given
+        let attestation = Attestation {
+            request_cid: [0x01; 32].into(),
+            response_cid: [0x02; 32].into(),
+            deployment: [0x03; 32].into(),
+            r: Default::default(),
+            s: Default::default(),
+            v: 27,
+        };
+        let payload = serialize_attestation(
+            &attestation,
+            Address::repeat_byte(0x04),
+            "{}".to_string(),
+            r#"{"data":{}}"#.to_string(),
+        );
+        let payload = AttestationProtobuf::decode(payload.as_slice()).unwrap();
+
+        //* When
+        let json = serde_json::to_value(&payload).unwrap();
+
+        //* Then
+        assert_eq!(json["request"], "{}");
+        assert_eq!(json["response"], r#"{"data":{}}"#);
+        assert_eq!(json["allocation"], format!("0x{}", "04".repeat(20)));
+        assert_eq!(
+            json["subgraph_deployment"],
+            format!("0x{}", "03".repeat(32))
+        );
+        assert_eq!(json["request_cid"], format!("0x{}", "01".repeat(32)));
+        assert_eq!(json["response_cid"], format!("0x{}", "02".repeat(32)));
+        assert!(json["signature"].as_str().unwrap().starts_with("0x1b"));
+        assert!(json["response_omitted_elements"].is_null());
+    }
 }
@@ -1,5 +1,5 @@
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{BTreeMap, HashMap, HashSet},
     sync::Arc,
     time::Duration,
 };
@@ -18,7 +18,7 @@ use graph_gateway::network::{
     internal::{
         fetch_and_pre_process_indexers_info as internal_fetch_and_pre_process_indexers_info,
         fetch_update as internal_fetch_update, process_indexers_info, types as internal_types,
-        InternalState,
+        InternalState, StatuslessIndexingPolicy,
     },
     subgraph::Client,
     NetworkTopologySnapshot,
@@ -97,9 +97,13 @@ fn test_service_state(
         indexer_host_resolver: indexers_host_resolver,
         indexer_host_blocklist: None,
         indexer_version_resolver: indexers_version_resolver,
+        indexer_health_prober: None,
         indexer_indexing_pois_blocklist: None,
         indexer_indexing_status_resolver: indexers_indexing_status_resolver,
+        indexer_indexing_statusless_policy: StatuslessIndexingPolicy::default(),
         indexer_indexing_cost_model_resolver: indexers_cost_model_resolver,
+        deployment_blocklist: HashSet::new(),
+        chain_aliases: BTreeMap::new(),
     };
 
     if !addr_blocklist.is_empty() {